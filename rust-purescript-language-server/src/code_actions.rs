@@ -1,7 +1,14 @@
-use crate::ide_server::RebuildError;
+use crate::completion;
+use crate::document;
+use crate::ide_server::{
+    ErrorPosition, Filter, IdeClient, ImportCommand, RebuildError, commands as ide_commands,
+};
+use crate::imports;
+use anyhow::Result;
 use lsp_types::{
-    CodeAction, CodeActionKind, CodeActionParams, Position, Range, TextEdit, WorkspaceEdit,
+    CodeAction, CodeActionKind, CodeActionParams, Position, Range, TextEdit, Url, WorkspaceEdit,
 };
+use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
 /// Check if two ranges overlap
@@ -272,3 +279,732 @@ pub fn create_apply_all_action(
         data: None,
     })
 }
+
+/// A pattern variable that can be split into one clause per constructor
+#[derive(Debug, PartialEq)]
+struct CaseSplitTarget {
+    variable: String,
+    /// 0-based char columns of the variable within its line
+    begin: u32,
+    end: u32,
+    /// The variable's type, from the function's type signature
+    type_info: String,
+}
+
+/// Find the pattern variable under the cursor and its type
+///
+/// The variable's type is taken from the function's type signature, using the
+/// variable's argument position in the clause.
+fn case_split_target(content: &str, position: Position) -> Option<CaseSplitTarget> {
+    let line = content.lines().nth(position.line as usize)?;
+    let clause = document::parse_function_clause(line)?;
+
+    let cursor = position.character;
+    let (arg_index, (variable, begin, end)) = clause
+        .args
+        .iter()
+        .enumerate()
+        .find(|(_, (_, begin, end))| *begin <= cursor && cursor <= *end)?;
+
+    // Only plain variables can be split
+    if !variable.starts_with(|c: char| c.is_lowercase() || c == '_') || variable.contains('@') {
+        return None;
+    }
+
+    let signature = document::type_signature(content, &clause.name)?;
+    let types = document::split_function_type(&signature);
+    if arg_index + 1 >= types.len() {
+        return None;
+    }
+
+    Some(CaseSplitTarget {
+        variable: variable.clone(),
+        begin: *begin,
+        end: *end,
+        type_info: types[arg_index].clone(),
+    })
+}
+
+/// Create a "Case split" refactoring for the pattern variable under the cursor
+///
+/// The clauses are only asked from the IDE server when the action is resolved.
+pub fn case_split_action(uri: &Url, content: &str, range: &Range) -> Option<CodeAction> {
+    let target = case_split_target(content, range.start)?;
+
+    Some(deferred_action(
+        format!("Case split '{}'", target.variable),
+        CodeActionKind::REFACTOR_REWRITE,
+        None,
+        ResolveData::CaseSplit {
+            uri: uri.clone(),
+            position: range.start,
+        },
+    ))
+}
+
+/// Replace the clause at a position with one clause per constructor
+async fn case_split_edits(
+    ide: &IdeClient,
+    content: &str,
+    position: Position,
+) -> Result<Vec<TextEdit>> {
+    let (Some(line), Some(target)) = (
+        content.lines().nth(position.line as usize),
+        case_split_target(content, position),
+    ) else {
+        anyhow::bail!("There is no pattern variable to split here");
+    };
+
+    let lines =
+        ide_commands::case_split(ide, line, target.begin, target.end, &target.type_info).await?;
+    if lines.is_empty() {
+        return Ok(vec![]);
+    }
+
+    Ok(vec![TextEdit {
+        range: Range {
+            start: Position {
+                line: position.line,
+                character: 0,
+            },
+            end: Position {
+                line: position.line,
+                character: line.chars().count() as u32,
+            },
+        },
+        new_text: lines.join("\n"),
+    }])
+}
+
+/// Get the top-level type signature on a line, if the IDE server can add a clause for it
+fn clause_signature(content: &str, line_number: u32) -> Option<&str> {
+    let lines: Vec<&str> = content.lines().collect();
+    let line = lines.get(line_number as usize)?;
+
+    let signature_re = regex::Regex::new(r"^[a-z_][A-Za-z0-9_']*\s*::").unwrap();
+    if !signature_re.is_match(line) {
+        return None;
+    }
+
+    // The IDE server only understands signatures on a single line
+    if lines
+        .get(line_number as usize + 1)
+        .is_some_and(|next| next.starts_with(char::is_whitespace) && !next.trim().is_empty())
+    {
+        return None;
+    }
+
+    Some(line)
+}
+
+/// Create an "Add function clause" action for a top-level type signature on the cursor line
+///
+/// The clause is only asked from the IDE server when the action is resolved.
+pub fn add_clause_action(uri: &Url, content: &str, range: &Range) -> Option<CodeAction> {
+    clause_signature(content, range.start.line)?;
+
+    Some(deferred_action(
+        "Add function clause".to_string(),
+        CodeActionKind::REFACTOR,
+        None,
+        ResolveData::AddClause {
+            uri: uri.clone(),
+            line: range.start.line,
+        },
+    ))
+}
+
+/// Insert a clause for the type signature on a line below it
+async fn add_clause_edits(
+    ide: &IdeClient,
+    content: &str,
+    line_number: u32,
+) -> Result<Vec<TextEdit>> {
+    let Some(line) = clause_signature(content, line_number) else {
+        anyhow::bail!("There is no type signature to add a clause for here");
+    };
+
+    let clause_lines: Vec<String> = ide_commands::add_clause(ide, line)
+        .await?
+        .into_iter()
+        .filter(|clause_line| clause_line.trim_end() != line.trim_end())
+        .collect();
+    if clause_lines.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let insert_position = Position {
+        line: line_number + 1,
+        character: 0,
+    };
+    Ok(vec![TextEdit {
+        range: Range {
+            start: insert_position,
+            end: insert_position,
+        },
+        new_text: format!("{}\n", clause_lines.join("\n")),
+    }])
+}
+
+/// Error codes for names that are not in scope, which a new import may fix
+const UNKNOWN_NAME_ERRORS: &[&str] = &[
+    "UnknownName",
+    "UnknownType",
+    "UnknownDataConstructor",
+    "UnknownClass",
+];
+
+/// Maximum number of candidate modules offered for a missing import
+const MAX_IMPORT_CANDIDATES: usize = 5;
+
+/// Create "Import X from Module" quick fixes for unknown names in the requested range
+///
+/// Candidate modules come from the IDE server. Unqualified names get an explicit
+/// import plus a variant that imports the module qualified and qualifies the usage;
+/// qualified names (`Map.insert`) get `import Module as Map`. The import edits are
+/// only computed when an action is resolved.
+pub async fn missing_import_actions(
+    ide: &IdeClient,
+    params: &CodeActionParams,
+    content: &str,
+    errors: &[RebuildError],
+) -> Result<Vec<CodeAction>> {
+    let uri = &params.text_document.uri;
+    let mut actions = Vec::new();
+
+    for error in errors {
+        if !UNKNOWN_NAME_ERRORS.contains(&error.error_code.as_str()) {
+            continue;
+        }
+
+        let range = error_range(&error.position);
+        if !ranges_overlap(&range, &params.range) {
+            continue;
+        }
+
+        let Some(identifier) = document::identifier_at(content, range.start) else {
+            continue;
+        };
+
+        let candidates = ide_commands::complete(
+            ide,
+            &[Filter::Exact(identifier.name.clone())],
+            None,
+            MAX_IMPORT_CANDIDATES * 4,
+        )
+        .await?;
+
+        let mut seen = std::collections::HashSet::new();
+        let modules: Vec<String> = candidates
+            .iter()
+            .map(completion::import_module)
+            .filter(|module| seen.insert(module.clone()))
+            .take(MAX_IMPORT_CANDIDATES)
+            .collect();
+
+        for module in modules {
+            match &identifier.qualifier {
+                Some(qualifier) => {
+                    actions.push(deferred_action(
+                        format!("Import {} as {}", module, qualifier),
+                        CodeActionKind::QUICKFIX,
+                        Some(false),
+                        ResolveData::ImportModule {
+                            uri: uri.clone(),
+                            module: module.clone(),
+                            qualifier: qualifier.clone(),
+                            edit: None,
+                        },
+                    ));
+                }
+                None => {
+                    actions.push(deferred_action(
+                        format!("Import {} from {}", identifier.name, module),
+                        CodeActionKind::QUICKFIX,
+                        Some(false),
+                        ResolveData::ImportIdentifier {
+                            uri: uri.clone(),
+                            identifier: identifier.name.clone(),
+                            module: module.clone(),
+                            edit: None,
+                        },
+                    ));
+
+                    // Qualified variant: `import Data.Map as Map` and `insert` -> `Map.insert`
+                    let qualifier = module.rsplit('.').next().unwrap_or(&module).to_string();
+                    actions.push(deferred_action(
+                        format!(
+                            "Import {} as {} and use {}.{}",
+                            module, qualifier, qualifier, identifier.name
+                        ),
+                        CodeActionKind::QUICKFIX,
+                        Some(false),
+                        ResolveData::ImportModule {
+                            uri: uri.clone(),
+                            module: module.clone(),
+                            edit: Some(TextEdit {
+                                range: Range {
+                                    start: identifier.range.start,
+                                    end: identifier.range.start,
+                                },
+                                new_text: format!("{}.", qualifier),
+                            }),
+                            qualifier,
+                        },
+                    ));
+                }
+            }
+        }
+    }
+
+    Ok(actions)
+}
+
+/// Maximum number of fill suggestions offered per typed hole
+const MAX_HOLE_FILLS: usize = 10;
+
+/// Offer to fill typed holes (`?name`) with the values the compiler suggests for them
+///
+/// Suggestions that are not in scope yet also get the import that brings them in,
+/// which is computed when the action is resolved.
+pub async fn fill_hole_actions(
+    ide: &IdeClient,
+    params: &CodeActionParams,
+    content: &str,
+    errors: &[RebuildError],
+) -> Result<Vec<CodeAction>> {
+    let uri = &params.text_document.uri;
+    let current_module = document::module_name(content);
+    let imports = document::parse_imports(content);
+    let mut actions = Vec::new();
+
+    for error in errors {
+        if error.error_code != "HoleInferredType" {
+            continue;
+        }
+        let Some(hole) = &error.hole_completions else {
+            continue;
+        };
+
+        let range = error_range(&error.position);
+        if !ranges_overlap(&range, &params.range) {
+            continue;
+        }
+
+        for completion in hole.completions.iter().take(MAX_HOLE_FILLS) {
+            let identifier = &completion.identifier;
+            let value = if identifier.starts_with(|c: char| document::is_operator_char(c)) {
+                format!("({})", identifier)
+            } else {
+                identifier.clone()
+            };
+
+            let mut import_module = None;
+
+            // Local values, record fields and the module's own declarations need no import
+            let needs_import = !completion.module.is_empty()
+                && Some(&completion.module) != current_module.as_ref()
+                && !completion.module.starts_with("Prim");
+
+            let value = if !needs_import {
+                value
+            } else if let Some(qualifier) = imports
+                .iter()
+                .find(|import| import.module == completion.module)
+                .and_then(|import| import.qualifier.as_ref())
+            {
+                format!("{}.{}", qualifier, value)
+            } else {
+                let in_scope = ide_commands::type_search_in_module(ide, content, None, identifier)
+                    .await?
+                    .iter()
+                    .any(|info| {
+                        info.module == completion.module
+                            || info.exported_from.contains(&completion.module)
+                    });
+                if !in_scope {
+                    import_module = Some(completion.module.clone());
+                }
+                value
+            };
+
+            let fill = TextEdit {
+                range,
+                new_text: value.clone(),
+            };
+            actions.push(match import_module {
+                Some(module) => deferred_action(
+                    format!("Fill hole ?{} with {} from {}", hole.name, value, module),
+                    CodeActionKind::QUICKFIX,
+                    Some(false),
+                    ResolveData::ImportIdentifier {
+                        uri: uri.clone(),
+                        identifier: identifier.clone(),
+                        module,
+                        edit: Some(fill),
+                    },
+                ),
+                None => quick_fix_action(
+                    format!("Fill hole ?{} with {}", hole.name, value),
+                    uri,
+                    vec![fill],
+                ),
+            });
+        }
+    }
+
+    Ok(actions)
+}
+
+fn error_range(position: &ErrorPosition) -> Range {
+    Range {
+        start: Position {
+            line: position.start_line.saturating_sub(1),
+            character: position.start_column.saturating_sub(1),
+        },
+        end: Position {
+            line: position.end_line.saturating_sub(1),
+            character: position.end_column.saturating_sub(1),
+        },
+    }
+}
+
+fn quick_fix_action(title: String, uri: &Url, edits: Vec<TextEdit>) -> CodeAction {
+    CodeAction {
+        title,
+        kind: Some(CodeActionKind::QUICKFIX),
+        diagnostics: None,
+        is_preferred: Some(false),
+        disabled: None,
+        edit: Some(WorkspaceEdit {
+            changes: Some(HashMap::from([(uri.clone(), edits)])),
+            document_changes: None,
+            change_annotations: None,
+        }),
+        command: None,
+        data: None,
+    }
+}
+
+/// Edits of a code action that need the IDE server, computed by `codeAction/resolve`
+///
+/// This is stored in the action's `data`, so only the action the user picks runs
+/// the IDE queries.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(tag = "action", rename_all = "camelCase")]
+pub enum ResolveData {
+    /// Import an identifier from a module, then apply `edit`
+    ImportIdentifier {
+        uri: Url,
+        identifier: String,
+        module: String,
+        edit: Option<TextEdit>,
+    },
+    /// Import a module under a qualifier, then apply `edit`
+    ImportModule {
+        uri: Url,
+        module: String,
+        qualifier: String,
+        edit: Option<TextEdit>,
+    },
+    /// Split the pattern variable at a position
+    CaseSplit { uri: Url, position: Position },
+    /// Add a clause for the type signature on a line
+    AddClause { uri: Url, line: u32 },
+}
+
+impl ResolveData {
+    /// The document the action edits
+    pub fn uri(&self) -> &Url {
+        match self {
+            ResolveData::ImportIdentifier { uri, .. }
+            | ResolveData::ImportModule { uri, .. }
+            | ResolveData::CaseSplit { uri, .. }
+            | ResolveData::AddClause { uri, .. } => uri,
+        }
+    }
+}
+
+/// Create a code action whose edit is computed when it is resolved
+fn deferred_action(
+    title: String,
+    kind: CodeActionKind,
+    is_preferred: Option<bool>,
+    data: ResolveData,
+) -> CodeAction {
+    CodeAction {
+        title,
+        kind: Some(kind),
+        diagnostics: None,
+        is_preferred,
+        disabled: None,
+        edit: None,
+        command: None,
+        data: serde_json::to_value(data).ok(),
+    }
+}
+
+/// Compute the edit of a deferred code action against the document's current content
+pub async fn resolve_code_action(
+    ide: &IdeClient,
+    content: &str,
+    data: &ResolveData,
+) -> Result<WorkspaceEdit> {
+    let edits = match data {
+        ResolveData::ImportIdentifier {
+            identifier,
+            module,
+            edit,
+            ..
+        } => {
+            let import_command = ImportCommand::Identifier {
+                identifier: identifier.clone(),
+                qualifier: None,
+            };
+            let filters = [
+                Filter::Exact(identifier.clone()),
+                Filter::Modules(vec![module.clone()]),
+            ];
+            let mut edits = imports::import_edits(ide, content, &import_command, &filters).await?;
+            edits.extend(edit.clone());
+            edits
+        }
+        ResolveData::ImportModule {
+            module,
+            qualifier,
+            edit,
+            ..
+        } => {
+            let import_command = ImportCommand::Qualified {
+                module: module.clone(),
+                qualifier: qualifier.clone(),
+            };
+            let mut edits = imports::import_edits(ide, content, &import_command, &[]).await?;
+            edits.extend(edit.clone());
+            edits
+        }
+        ResolveData::CaseSplit { position, .. } => {
+            case_split_edits(ide, content, *position).await?
+        }
+        ResolveData::AddClause { line, .. } => add_clause_edits(ide, content, *line).await?,
+    };
+
+    Ok(WorkspaceEdit {
+        changes: Some(HashMap::from([(data.uri().clone(), edits)])),
+        document_changes: None,
+        change_annotations: None,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn uri() -> Url {
+        Url::parse("file:///project/src/Main.purs").unwrap()
+    }
+
+    fn position(start: (u32, u32), end: (u32, u32)) -> serde_json::Value {
+        json!({
+            "startLine": start.0,
+            "startColumn": start.1,
+            "endLine": end.0,
+            "endColumn": end.1
+        })
+    }
+
+    fn error(
+        code: &str,
+        start: (u32, u32),
+        end: (u32, u32),
+        suggestion: serde_json::Value,
+    ) -> RebuildError {
+        serde_json::from_value(json!({
+            "allSpans": [],
+            "errorCode": code,
+            "errorLink": null,
+            "filename": "src/Main.purs",
+            "message": "",
+            "moduleName": "Main",
+            "position": position(start, end),
+            "suggestion": suggestion
+        }))
+        .unwrap()
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range {
+            start: Position {
+                line: start.0,
+                character: start.1,
+            },
+            end: Position {
+                line: end.0,
+                character: end.1,
+            },
+        }
+    }
+
+    fn edits(action: &CodeAction) -> Vec<TextEdit> {
+        action.edit.as_ref().unwrap().changes.as_ref().unwrap()[&uri()].clone()
+    }
+
+    #[test]
+    fn suggestion_replaces_its_range() {
+        let error = error(
+            "UnusedImport",
+            (3, 1),
+            (3, 21),
+            json!({ "replacement": "", "replace_range": position((3, 1), (4, 1)) }),
+        );
+
+        let action = error_to_code_action(&error, &uri()).unwrap();
+        assert_eq!(action.title, "Remove import");
+        assert_eq!(action.kind, Some(CodeActionKind::QUICKFIX));
+        assert_eq!(
+            edits(&action),
+            vec![TextEdit {
+                range: range((2, 0), (3, 0)),
+                new_text: String::new(),
+            }]
+        );
+    }
+
+    #[test]
+    fn type_annotation_is_inserted_above_declaration() {
+        let error = error(
+            "MissingTypeDeclaration",
+            (8, 1),
+            (8, 18),
+            json!({ "replacement": "main :: Effect Unit\n", "replace_range": null }),
+        );
+
+        let action = error_to_code_action(&error, &uri()).unwrap();
+        assert_eq!(
+            edits(&action),
+            vec![TextEdit {
+                range: range((7, 0), (7, 0)),
+                new_text: "main :: Effect Unit\n".to_string(),
+            }]
+        );
+    }
+
+    #[test]
+    fn apply_all_skips_overlapping_fixes() {
+        let params: CodeActionParams = serde_json::from_value(json!({
+            "textDocument": { "uri": uri() },
+            "range": range((0, 0), (0, 0)),
+            "context": { "diagnostics": [] }
+        }))
+        .unwrap();
+        let remove = |line: u32| json!({ "replacement": "", "replace_range": position((line, 1), (line + 1, 1)) });
+        let errors = vec![
+            error("UnusedImport", (3, 1), (3, 15), remove(3)),
+            error("UnusedImport", (5, 1), (5, 15), remove(5)),
+            error("DuplicateImport", (5, 1), (5, 10), remove(5)),
+        ];
+
+        let action = create_apply_all_action(&params, &errors).unwrap();
+        assert_eq!(action.title, "Apply all fixes (2 fixes)");
+        // Fixes apply from the end of the file to the start
+        assert_eq!(
+            edits(&action)
+                .iter()
+                .map(|edit| edit.range)
+                .collect::<Vec<_>>(),
+            vec![range((4, 0), (5, 0)), range((2, 0), (3, 0))]
+        );
+    }
+
+    #[test]
+    fn case_split_target_under_cursor() {
+        let content = "f :: Maybe Int -> Boolean -> Int\nf m flag = 0\n";
+        let target = |character| case_split_target(content, Position { line: 1, character });
+
+        assert_eq!(
+            target(2),
+            Some(CaseSplitTarget {
+                variable: "m".to_string(),
+                begin: 2,
+                end: 3,
+                type_info: "Maybe Int".to_string(),
+            })
+        );
+        assert_eq!(
+            target(6),
+            Some(CaseSplitTarget {
+                variable: "flag".to_string(),
+                begin: 4,
+                end: 8,
+                type_info: "Boolean".to_string(),
+            })
+        );
+        // Not on an argument
+        assert_eq!(target(0), None);
+        assert_eq!(target(11), None);
+    }
+
+    #[test]
+    fn case_split_needs_a_plain_variable_and_signature() {
+        let content = "f :: Maybe Int -> Int\nf (Just x) = x\ng y = y\n";
+        assert_eq!(
+            case_split_target(
+                content,
+                Position {
+                    line: 1,
+                    character: 4
+                }
+            ),
+            None
+        );
+        assert_eq!(
+            case_split_target(
+                content,
+                Position {
+                    line: 2,
+                    character: 2
+                }
+            ),
+            None
+        );
+    }
+
+    #[test]
+    fn clause_signature_on_a_single_line() {
+        let content = "f :: Int -> Int\n\ng\n  :: Int\n  -> Int\nh ::\n  Int -> Int\n";
+        assert_eq!(clause_signature(content, 0), Some("f :: Int -> Int"));
+        assert_eq!(clause_signature(content, 2), None);
+        assert_eq!(clause_signature(content, 3), None);
+        assert_eq!(clause_signature(content, 5), None);
+    }
+
+    #[test]
+    fn refactorings_are_resolved_later() {
+        let content = "f :: Maybe Int -> Int\nf m = 0\n";
+
+        let action = case_split_action(&uri(), content, &range((1, 2), (1, 2))).unwrap();
+        assert_eq!(action.title, "Case split 'm'");
+        assert_eq!(action.edit, None);
+        assert_eq!(
+            serde_json::from_value::<ResolveData>(action.data.unwrap()).unwrap(),
+            ResolveData::CaseSplit {
+                uri: uri(),
+                position: Position {
+                    line: 1,
+                    character: 2
+                },
+            }
+        );
+
+        let action = add_clause_action(&uri(), content, &range((0, 3), (0, 3))).unwrap();
+        assert_eq!(action.edit, None);
+        assert_eq!(
+            serde_json::from_value::<ResolveData>(action.data.unwrap()).unwrap(),
+            ResolveData::AddClause {
+                uri: uri(),
+                line: 0
+            }
+        );
+    }
+}
@@ -1,11 +1,15 @@
 use crate::build;
+use crate::diagnostic_store::DiagnosticSource;
 use crate::diagnostics;
+use crate::ide_server::RebuildError;
+use crate::inlay_hints;
 use crate::types::ServerState;
 use lsp_types::{
-    MessageType, NumberOrString, ProgressParams, ProgressParamsValue, WorkDoneProgress,
+    MessageType, NumberOrString, ProgressParams, ProgressParamsValue, Url, WorkDoneProgress,
     WorkDoneProgressBegin, WorkDoneProgressCreateParams, WorkDoneProgressEnd,
     WorkDoneProgressReport, notification::Progress, request::WorkDoneProgressCreate,
 };
+use std::collections::HashMap;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tower_lsp::Client;
@@ -16,10 +20,13 @@ pub async fn execute(
     state: &Arc<Mutex<ServerState>>,
     quick: bool,
 ) -> Result<(), String> {
-    // Get workspace root
-    let workspace_root = {
-        let state = state.lock().await;
-        state.workspace_root.clone()
+    // Get workspace root and the generation of this build's results
+    let (workspace_root, generation) = {
+        let mut state = state.lock().await;
+        (
+            state.workspace_root.clone(),
+            state.diagnostics.next_generation(),
+        )
     };
 
     let Some(workspace_root) = workspace_root else {
@@ -153,52 +160,11 @@ pub async fn execute(
                     })
                     .await;
 
-                // Clear diagnostics for all .purs files from previous builds
-                // This includes both last_build_errors and document_errors
-                {
-                    let state = state.lock().await;
-                    let mut all_uris = std::collections::HashSet::new();
-
-                    // Collect URIs from both error sources
-                    for uri in state.last_build_errors.keys() {
-                        all_uris.insert(uri.clone());
-                    }
-                    for uri in state.document_errors.keys() {
-                        all_uris.insert(uri.clone());
-                    }
-
-                    // Clear diagnostics for all previously tracked files
-                    for uri in all_uris {
-                        if uri.path().ends_with(".purs") {
-                            client.publish_diagnostics(uri, vec![], None).await;
-                        }
-                    }
-                }
-
-                // Clear previous build errors and document errors
-                {
-                    let mut state = state.lock().await;
-                    state.last_build_errors.clear();
-                    state.document_errors.clear();
-                }
-
-                // Publish diagnostics for all files with errors/warnings
-                let mut all_uris = std::collections::HashSet::new();
-
-                // Process errors
+                // Errors and warnings of this build replace those of the previous build
+                let mut results: HashMap<Url, Vec<RebuildError>> = HashMap::new();
                 for (file_path, errors) in &build_result.errors {
                     if let Some(uri) = build::file_path_to_uri(file_path, &workspace_root) {
-                        all_uris.insert(uri.clone());
-
-                        // Store errors in state
-                        {
-                            let mut state = state.lock().await;
-                            state.last_build_errors.insert(uri.clone(), errors.clone());
-                        }
-
-                        // Publish diagnostics
-                        let diagnostics = diagnostics::convert_rebuild_errors(errors, &uri);
-                        client.publish_diagnostics(uri, diagnostics, None).await;
+                        results.entry(uri).or_default().extend(errors.clone());
                     }
                 }
 
@@ -207,24 +173,26 @@ pub async fn execute(
                     // Only show warnings for files in the workspace, not deps
                     if !file_path.contains(".spago") && !file_path.contains("..") {
                         if let Some(uri) = build::file_path_to_uri(file_path, &workspace_root) {
-                            all_uris.insert(uri.clone());
-
-                            // Store warnings in state
-                            {
-                                let mut state = state.lock().await;
-                                let existing = state
-                                    .last_build_errors
-                                    .entry(uri.clone())
-                                    .or_insert_with(Vec::new);
-                                existing.extend(warnings.clone());
-                            }
-
-                            // Publish diagnostics
-                            let diagnostics = diagnostics::convert_rebuild_errors(warnings, &uri);
-                            client.publish_diagnostics(uri, diagnostics, None).await;
+                            results.entry(uri).or_default().extend(warnings.clone());
                         }
                     }
                 }
+
+                let changed_uris = {
+                    let mut state = state.lock().await;
+                    state
+                        .diagnostics
+                        .replace_source(DiagnosticSource::Build, generation, results)
+                };
+
+                // Publish the merged diagnostics of every file this build changed
+                for uri in changed_uris {
+                    if uri.path().ends_with(".purs") {
+                        diagnostics::publish(&client, &state, &uri).await;
+                    }
+                }
+
+                inlay_hints::refresh(&client, &state).await;
             }
             Err(e) => {
                 client
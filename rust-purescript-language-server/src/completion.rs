@@ -0,0 +1,247 @@
+use crate::document;
+use crate::hover;
+use crate::ide_server::{
+    CompletionInfo, Filter, IdeClient, ImportCommand, commands as ide_commands,
+};
+use crate::imports;
+use anyhow::Result;
+use lsp_types::{
+    CompletionItem, CompletionItemKind, CompletionItemLabelDetails, CompletionTextEdit,
+    Documentation, MarkupContent, MarkupKind, Position, TextEdit, Url,
+};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+
+/// Maximum number of completions requested from the IDE server per query
+const MAX_RESULTS: usize = 50;
+
+/// Import to add when a completion item from an unimported module is accepted
+///
+/// Stored in `CompletionItem::data` and turned into edits on `completionItem/resolve`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AutoImport {
+    pub uri: Url,
+    pub module: String,
+    pub identifier: String,
+    pub qualifier: Option<String>,
+}
+
+/// Get completions for the word being typed at a position
+///
+/// Results in scope through the module's imports (for the typed qualifier, if any)
+/// and the module's own declarations come first. Declarations from modules that are
+/// not imported yet follow, carrying an `AutoImport` to add the import on accept.
+pub async fn complete(
+    ide: &IdeClient,
+    uri: &Url,
+    content: &str,
+    position: Position,
+) -> Result<Vec<CompletionItem>> {
+    let Some(prefix) = document::completion_prefix_at(content, position) else {
+        return Ok(vec![]);
+    };
+
+    let current_module = document::module_name(content);
+    let prefix_filter = Filter::Prefix(prefix.prefix.clone());
+
+    let mut results = ide_commands::complete(
+        ide,
+        &[
+            prefix_filter.clone(),
+            Filter::Dependencies {
+                qualifier: prefix.qualifier.clone(),
+                module_text: document::module_header(content),
+            },
+        ],
+        current_module.as_deref(),
+        MAX_RESULTS,
+    )
+    .await?;
+
+    // Unqualified names can also refer to the module's own declarations
+    if let (None, Some(current_module)) = (&prefix.qualifier, &current_module) {
+        results.extend(
+            ide_commands::complete(
+                ide,
+                &[prefix_filter, Filter::Modules(vec![current_module.clone()])],
+                Some(current_module),
+                MAX_RESULTS,
+            )
+            .await?,
+        );
+    }
+
+    let mut seen = HashSet::new();
+    let mut items: Vec<CompletionItem> = results
+        .into_iter()
+        .filter(|info| seen.insert((info.module.clone(), info.identifier.clone())))
+        .map(|info| completion_info_to_item(&info, &prefix.range))
+        .collect();
+
+    // A qualifier that is already imported only ever refers to that import
+    let imports = document::parse_imports(content);
+    let qualifier_imported = prefix
+        .qualifier
+        .as_deref()
+        .is_some_and(|qualifier| !document::modules_for_qualifier(&imports, qualifier).is_empty());
+
+    if !qualifier_imported {
+        let candidates = ide_commands::complete(
+            ide,
+            &[Filter::Prefix(prefix.prefix.clone())],
+            current_module.as_deref(),
+            MAX_RESULTS,
+        )
+        .await?;
+
+        let candidates: Vec<CompletionInfo> = candidates
+            .into_iter()
+            .filter(|info| seen.insert((info.module.clone(), info.identifier.clone())))
+            .filter(|info| Some(&info.module) != current_module.as_ref())
+            .collect();
+
+        items.extend(
+            candidates_for_qualifier(candidates, prefix.qualifier.as_deref())
+                .iter()
+                .map(|info| {
+                    let mut item = completion_info_to_item(info, &prefix.range);
+                    let module = import_module(info);
+                    item.label_details = Some(CompletionItemLabelDetails {
+                        detail: Some(" (auto-import)".to_string()),
+                        description: Some(module.clone()),
+                    });
+                    item.sort_text = Some(format!("1{}", info.identifier));
+                    item.data = serde_json::to_value(AutoImport {
+                        uri: uri.clone(),
+                        module,
+                        identifier: info.identifier.clone(),
+                        qualifier: prefix.qualifier.clone(),
+                    })
+                    .ok();
+                    item
+                }),
+        );
+    }
+
+    Ok(items)
+}
+
+/// Resolve a completion item, attaching the import edits for an `AutoImport`
+pub async fn resolve(
+    ide: &IdeClient,
+    content: &str,
+    mut item: CompletionItem,
+) -> Result<CompletionItem> {
+    let Some(auto_import) = item
+        .data
+        .clone()
+        .and_then(|data| serde_json::from_value::<AutoImport>(data).ok())
+    else {
+        return Ok(item);
+    };
+
+    let (import_command, filters) = match auto_import.qualifier {
+        Some(qualifier) => (
+            ImportCommand::Qualified {
+                module: auto_import.module,
+                qualifier,
+            },
+            vec![],
+        ),
+        None => (
+            ImportCommand::Identifier {
+                identifier: auto_import.identifier.clone(),
+                qualifier: None,
+            },
+            vec![
+                Filter::Exact(auto_import.identifier),
+                Filter::Modules(vec![auto_import.module]),
+            ],
+        ),
+    };
+
+    let edits = imports::import_edits(ide, content, &import_command, &filters).await?;
+    if !edits.is_empty() {
+        item.additional_text_edits = Some(edits);
+    }
+
+    Ok(item)
+}
+
+/// Narrow qualified candidates to modules the qualifier plausibly abbreviates
+///
+/// `Map.ins` should suggest `Data.Map.insert`, not every `insert` in the package
+/// set. If no module matches, all candidates are kept.
+fn candidates_for_qualifier(
+    candidates: Vec<CompletionInfo>,
+    qualifier: Option<&str>,
+) -> Vec<CompletionInfo> {
+    let Some(qualifier) = qualifier else {
+        return candidates;
+    };
+    let last_segment = qualifier.rsplit('.').next().unwrap_or(qualifier);
+
+    let matching: Vec<CompletionInfo> = candidates
+        .iter()
+        .filter(|info| {
+            let module = import_module(info);
+            module == qualifier || module.rsplit('.').next() == Some(last_segment)
+        })
+        .cloned()
+        .collect();
+
+    if matching.is_empty() {
+        candidates
+    } else {
+        matching
+    }
+}
+
+/// Pick the module to import a declaration from
+///
+/// Prefers public re-exports (e.g. `Data.Map` over `Data.Map.Internal`), then the
+/// shortest module name.
+pub fn import_module(info: &CompletionInfo) -> String {
+    info.exported_from
+        .iter()
+        .chain(std::iter::once(&info.module))
+        .min_by_key(|module| (module.contains("Internal"), module.len()))
+        .cloned()
+        .unwrap_or_else(|| info.module.clone())
+}
+
+/// Convert an IDE server completion into an LSP completion item
+fn completion_info_to_item(info: &CompletionInfo, range: &lsp_types::Range) -> CompletionItem {
+    CompletionItem {
+        label: info.identifier.clone(),
+        label_details: Some(CompletionItemLabelDetails {
+            detail: None,
+            description: Some(info.module.clone()),
+        }),
+        kind: Some(completion_kind(info)),
+        detail: Some(info.type_info.clone()),
+        documentation: Some(Documentation::MarkupContent(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: hover::render_completion_info(info),
+        })),
+        text_edit: Some(CompletionTextEdit::Edit(TextEdit {
+            range: *range,
+            new_text: info.identifier.clone(),
+        })),
+        ..Default::default()
+    }
+}
+
+/// Map the IDE server's declaration type to a completion item kind
+fn completion_kind(info: &CompletionInfo) -> CompletionItemKind {
+    match info.declaration_type.as_deref() {
+        Some("value") if info.type_info.contains("->") => CompletionItemKind::FUNCTION,
+        Some("value") => CompletionItemKind::VALUE,
+        Some("type") | Some("synonym") => CompletionItemKind::STRUCT,
+        Some("dataconstructor") => CompletionItemKind::CONSTRUCTOR,
+        Some("typeclass") => CompletionItemKind::INTERFACE,
+        Some("valueoperator") | Some("typeoperator") => CompletionItemKind::OPERATOR,
+        Some("module") => CompletionItemKind::MODULE,
+        _ => CompletionItemKind::VALUE,
+    }
+}
@@ -1,5 +1,7 @@
-use anyhow::Result;
+use anyhow::{Context, Result};
 use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::path::{Path, PathBuf};
 use tower_lsp::Client;
 use tower_lsp::lsp_types::{ConfigurationItem, MessageType};
 
@@ -9,16 +11,30 @@ use tower_lsp::lsp_types::{ConfigurationItem, MessageType};
 /// and ClientConfig (for user preferences), never deserialized directly.
 ///
 /// If ragu fails, initialization will fail - there are no fallback defaults.
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, Serialize, PartialEq)]
 pub struct Config {
     pub output_dir: String,
     pub source_globs: Vec<String>,
     pub formatter: Formatter,
     pub fast_rebuild_on_save: bool,
     pub fast_rebuild_on_change: bool,
+    pub inlay_hints: InlayHintsConfig,
+    pub diagnostics: DiagnosticsConfig,
+    /// Attach to this IDE server instead of starting one
+    pub external_ide_server: Option<ExternalIdeServer>,
 }
 
 impl Config {
+    /// Check if switching to another config needs the IDE server to be restarted
+    ///
+    /// Only the settings the IDE server is started with need a restart; the others
+    /// are read from the config whenever they are used.
+    pub fn needs_restart(&self, other: &Config) -> bool {
+        self.output_dir != other.output_dir
+            || self.source_globs != other.source_globs
+            || self.external_ide_server != other.external_ide_server
+    }
+
     /// Merge this config with values from a client config, preferring client values when present
     pub fn merge_with_client_config(&mut self, client_config: ClientConfig) {
         if let Some(formatter) = client_config.formatter {
@@ -30,6 +46,68 @@ impl Config {
         if let Some(fast_rebuild_on_change) = client_config.fast_rebuild_on_change {
             self.fast_rebuild_on_change = fast_rebuild_on_change;
         }
+        if let Some(inlay_hints) = client_config.inlay_hints {
+            self.inlay_hints = inlay_hints;
+        }
+        if let Some(diagnostic_severity) = client_config.diagnostic_severity {
+            self.diagnostics.severity_overrides = diagnostic_severity;
+        }
+        if let Some(censor_codes) = client_config.censor_codes {
+            self.diagnostics.censor_codes = censor_codes;
+        }
+        if let Some(censor_files) = client_config.censor_files {
+            self.diagnostics.censor_files = censor_files;
+        }
+        if let Some(external_ide_server) = client_config.external_ide_server {
+            self.external_ide_server = Some(external_ide_server);
+        }
+    }
+
+    /// Check if a file is one of the project's own sources
+    ///
+    /// The file must match a source glob and must not be a dependency under `.spago`.
+    pub fn is_project_file(&self, workspace_root: &str, file_path: &str) -> bool {
+        let path = Path::new(file_path);
+        let absolute = if path.is_absolute() {
+            path.to_path_buf()
+        } else {
+            Path::new(workspace_root).join(path)
+        };
+        let relative = absolute.strip_prefix(workspace_root).unwrap_or(&absolute);
+
+        if relative.components().any(|c| c.as_os_str() == ".spago") {
+            return false;
+        }
+
+        self.source_globs.iter().any(|source_glob| {
+            glob::Pattern::new(source_glob).is_ok_and(|pattern| {
+                pattern.matches_path(relative) || pattern.matches_path(&absolute)
+            })
+        })
+    }
+
+    /// Expand the source globs into the project's own source files
+    pub fn project_files(&self, workspace_root: &str) -> Vec<PathBuf> {
+        self.source_globs
+            .iter()
+            .filter_map(|source_glob| {
+                let pattern = if Path::new(source_glob).is_absolute() {
+                    source_glob.clone()
+                } else {
+                    Path::new(workspace_root)
+                        .join(source_glob)
+                        .to_string_lossy()
+                        .to_string()
+                };
+                glob::glob(&pattern).ok()
+            })
+            .flatten()
+            .filter_map(|entry| entry.ok())
+            .filter(|path| {
+                path.to_str()
+                    .is_some_and(|path| self.is_project_file(workspace_root, path))
+            })
+            .collect()
     }
 }
 
@@ -44,6 +122,194 @@ pub struct ClientConfig {
     pub formatter: Option<Formatter>,
     pub fast_rebuild_on_save: Option<bool>,
     pub fast_rebuild_on_change: Option<bool>,
+    pub inlay_hints: Option<InlayHintsConfig>,
+    pub diagnostic_severity: Option<HashMap<String, SeverityOverride>>,
+    pub censor_codes: Option<Vec<String>>,
+    pub censor_files: Option<Vec<String>>,
+    pub external_ide_server: Option<ExternalIdeServer>,
+}
+
+/// A `purs ide server` started outside the language server, e.g. by a file watcher
+///
+/// Given as `{ "port": 4242 }` or `{ "portFile": ".psc-ide-port" }`, where a relative
+/// port file is resolved against the workspace root. The language server connects to
+/// it but never starts, restarts or stops it.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub enum ExternalIdeServer {
+    Port(u16),
+    PortFile(String),
+}
+
+impl ExternalIdeServer {
+    /// Resolve the port to connect to, reading the port file if needed
+    pub fn port(&self, workspace_root: &str) -> Result<u16> {
+        match self {
+            ExternalIdeServer::Port(port) => Ok(*port),
+            ExternalIdeServer::PortFile(port_file) => {
+                let path = Path::new(workspace_root).join(port_file);
+                let content = std::fs::read_to_string(&path).with_context(|| {
+                    format!("Failed to read IDE server port file {}", path.display())
+                })?;
+                content.trim().parse().with_context(|| {
+                    format!(
+                        "IDE server port file {} does not contain a port",
+                        path.display()
+                    )
+                })
+            }
+        }
+    }
+}
+
+/// Which categories of inferred type inlay hints to show
+///
+/// Missing fields keep their defaults, so `{ "lambdaParameters": true }` only
+/// turns on lambda parameter hints.
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "camelCase", default)]
+pub struct InlayHintsConfig {
+    /// Unannotated top-level bindings, from the compiler's warnings
+    pub top_level: bool,
+    /// Unannotated `let` and `where` bindings
+    ///
+    /// Off by default: the hints of every new version of a document cost two
+    /// typechecks of the module through the IDE server.
+    pub let_bindings: bool,
+    /// Parameters of lambdas such as `\x -> ...`, at the same cost as `let_bindings`
+    pub lambda_parameters: bool,
+}
+
+impl Default for InlayHintsConfig {
+    fn default() -> Self {
+        Self {
+            top_level: true,
+            let_bindings: false,
+            lambda_parameters: false,
+        }
+    }
+}
+
+/// How compiler errors and warnings are filtered and re-classified before publishing
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct DiagnosticsConfig {
+    /// Severity to use for an error code instead of the built-in one
+    pub severity_overrides: HashMap<String, SeverityOverride>,
+    /// Error codes that are never shown
+    pub censor_codes: Vec<String>,
+    /// Globs of files whose diagnostics are never shown (e.g. generated code)
+    pub censor_files: Vec<String>,
+    /// Warnings censored by the project's build configuration
+    pub project_censoring: ProjectCensoring,
+}
+
+impl DiagnosticsConfig {
+    /// Check if diagnostics for an error code in a file are hidden
+    ///
+    /// `file_path` is matched as given by the compiler (usually relative to the
+    /// workspace root) and as an absolute path.
+    pub fn is_censored(&self, error_code: &str, file_path: &str, absolute_path: &Path) -> bool {
+        self.censor_codes.iter().any(|code| code == error_code)
+            || self.severity_overrides.get(error_code) == Some(&SeverityOverride::Off)
+            || self.censor_files.iter().any(|censor_glob| {
+                glob::Pattern::new(censor_glob).is_ok_and(|pattern| {
+                    pattern.matches(file_path) || pattern.matches_path(absolute_path)
+                })
+            })
+    }
+}
+
+/// Warning censoring declared in `spago.yaml` or `ragu.yaml`, so the editor hides
+/// the same warnings as the command line build
+#[derive(Debug, Clone, Serialize, Default, PartialEq)]
+pub struct ProjectCensoring {
+    /// `censorProjectWarnings: all`
+    pub all: bool,
+    pub codes: Vec<String>,
+    /// Codes censored with `byPrefix`
+    pub prefixes: Vec<String>,
+}
+
+impl ProjectCensoring {
+    /// Check if a warning with this code is censored (errors never are)
+    pub fn censors_warning(&self, error_code: &str) -> bool {
+        self.all
+            || self.codes.iter().any(|code| code == error_code)
+            || self
+                .prefixes
+                .iter()
+                .any(|prefix| error_code.starts_with(prefix.as_str()))
+    }
+}
+
+/// Project configuration files that can declare warning censoring
+const PROJECT_CONFIG_FILES: &[&str] = &["ragu.yaml", "spago.yaml"];
+
+/// Read warning censoring from the project's build configuration
+///
+/// The censoring of every project configuration file is combined, so a `ragu.yaml`
+/// without censoring does not hide what `spago.yaml` declares. Missing or
+/// unreadable files censor nothing.
+pub fn load_project_censoring(working_dir: &str) -> ProjectCensoring {
+    let mut censoring = ProjectCensoring::default();
+
+    for file_name in PROJECT_CONFIG_FILES {
+        let Ok(content) = std::fs::read_to_string(Path::new(working_dir).join(file_name)) else {
+            continue;
+        };
+        if let Ok(config) = serde_yaml::from_str::<serde_yaml::Value>(&content) {
+            add_project_censoring(&mut censoring, &config);
+        }
+    }
+
+    censoring
+}
+
+/// Add the warning censoring declared in one project configuration file
+///
+/// Looks for `censorProjectWarnings` (or `censor_project_warnings`) and `censorCodes`
+/// under `package.build` or at the top level. Values are `all`, a list of codes, or
+/// a list of `byCode`/`byPrefix` entries.
+fn add_project_censoring(censoring: &mut ProjectCensoring, config: &serde_yaml::Value) {
+    let sections = [&config["package"]["build"], config];
+    let keys = [
+        "censorProjectWarnings",
+        "censor_project_warnings",
+        "censorCodes",
+        "censor_codes",
+    ];
+
+    for value in sections
+        .iter()
+        .flat_map(|section| keys.iter().map(move |key| &section[*key]))
+    {
+        match value {
+            serde_yaml::Value::String(all) if all == "all" => censoring.all = true,
+            serde_yaml::Value::Sequence(entries) => {
+                for entry in entries {
+                    if let Some(code) = entry.as_str() {
+                        censoring.codes.push(code.to_string());
+                    } else if let Some(code) = entry["byCode"].as_str() {
+                        censoring.codes.push(code.to_string());
+                    } else if let Some(prefix) = entry["byPrefix"].as_str() {
+                        censoring.prefixes.push(prefix.to_string());
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+}
+
+/// Severity an error code can be re-classified as, or `off` to hide it
+#[derive(Debug, Clone, Copy, Serialize, Deserialize, PartialEq)]
+#[serde(rename_all = "lowercase")]
+pub enum SeverityOverride {
+    Error,
+    Warning,
+    Info,
+    Hint,
+    Off,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq)]
@@ -62,8 +328,9 @@ impl Default for Formatter {
 
 /// Initialize configuration using ragu for defaults
 ///
-/// This queries ragu for the output directory and source globs,
-/// which can then be optionally overridden by client configuration.
+/// This queries ragu for the output directory and source globs, and reads warning
+/// censoring from the project's config file. These can then be optionally
+/// overridden by client configuration.
 pub fn init_from_ragu(working_dir: &str) -> Result<Config> {
     let output_dir = crate::ragu::get_output_dir(working_dir)?;
     let source_globs = crate::ragu::get_sources(working_dir)?;
@@ -74,6 +341,12 @@ pub fn init_from_ragu(working_dir: &str) -> Result<Config> {
         formatter: Formatter::PursFmt,
         fast_rebuild_on_save: true,
         fast_rebuild_on_change: true,
+        inlay_hints: InlayHintsConfig::default(),
+        diagnostics: DiagnosticsConfig {
+            project_censoring: load_project_censoring(working_dir),
+            ..Default::default()
+        },
+        external_ide_server: None,
     })
 }
 
@@ -176,4 +449,92 @@ pub async fn log_config(client: &Client, config: &Config) {
             format!("Fast rebuild on change: {}", config.fast_rebuild_on_change),
         )
         .await;
+    client
+        .log_message(
+            MessageType::INFO,
+            format!("Inlay hints: {:?}", config.inlay_hints),
+        )
+        .await;
+    client
+        .log_message(
+            MessageType::INFO,
+            format!("Diagnostics: {:?}", config.diagnostics),
+        )
+        .await;
+    if let Some(external_ide_server) = &config.external_ide_server {
+        client
+            .log_message(
+                MessageType::INFO,
+                format!("External IDE server: {:?}", external_ide_server),
+            )
+            .await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn censoring(yaml: &str) -> ProjectCensoring {
+        let mut censoring = ProjectCensoring::default();
+        add_project_censoring(&mut censoring, &serde_yaml::from_str(yaml).unwrap());
+        censoring
+    }
+
+    #[test]
+    fn censor_all_warnings() {
+        let censoring = censoring("package:\n  build:\n    censorProjectWarnings: all\n");
+        assert!(censoring.all);
+        assert!(censoring.censors_warning("ShadowedName"));
+    }
+
+    #[test]
+    fn censor_list_of_codes() {
+        let censoring = censoring("censor_codes:\n  - ShadowedName\n  - UnusedImport\n");
+        assert_eq!(censoring.codes, vec!["ShadowedName", "UnusedImport"]);
+        assert!(!censoring.all);
+        assert!(!censoring.censors_warning("MissingTypeDeclaration"));
+    }
+
+    #[test]
+    fn censor_by_code_and_prefix() {
+        let censoring = censoring(
+            "package:\n  build:\n    censorProjectWarnings:\n      - byCode: ShadowedName\n      - byPrefix: Unused\n",
+        );
+        assert_eq!(censoring.codes, vec!["ShadowedName"]);
+        assert_eq!(censoring.prefixes, vec!["Unused"]);
+        assert!(censoring.censors_warning("UnusedImport"));
+        assert!(!censoring.censors_warning("ImplicitImport"));
+    }
+
+    #[test]
+    fn no_censoring() {
+        assert_eq!(
+            censoring("package:\n  name: example\n"),
+            ProjectCensoring::default()
+        );
+    }
+
+    #[test]
+    fn censoring_of_all_project_files_is_combined() {
+        let dir = tempfile::TempDir::new().unwrap();
+        std::fs::write(dir.path().join("ragu.yaml"), "output: output\n").unwrap();
+        std::fs::write(
+            dir.path().join("spago.yaml"),
+            "package:\n  build:\n    censorProjectWarnings:\n      - ShadowedName\n",
+        )
+        .unwrap();
+
+        let censoring = load_project_censoring(dir.path().to_str().unwrap());
+        assert_eq!(censoring.codes, vec!["ShadowedName"]);
+
+        std::fs::write(
+            dir.path().join("ragu.yaml"),
+            "censorCodes:\n  - byPrefix: Unused\n",
+        )
+        .unwrap();
+        let censoring = load_project_censoring(dir.path().to_str().unwrap());
+        assert_eq!(censoring.codes, vec!["ShadowedName"]);
+        assert_eq!(censoring.prefixes, vec!["Unused"]);
+    }
 }
@@ -0,0 +1,76 @@
+use crate::build;
+use crate::document;
+use crate::ide_server::{ErrorSpan, Filter, IdeClient, commands as ide_commands};
+use crate::references;
+use anyhow::Result;
+use lsp_types::{Location, Position, Range};
+
+/// Find the definition of the identifier or imported module at a position
+///
+/// Locations come from the IDE server's `definedAt` spans, so this also jumps
+/// into dependency sources (e.g. under `.spago`). The declaration is resolved like
+/// for references and rename, so a type and a data constructor sharing a name are
+/// told apart by the position.
+pub async fn goto_definition(
+    ide: &IdeClient,
+    workspace_root: &str,
+    content: &str,
+    position: Position,
+) -> Result<Option<Location>> {
+    if let Some(module) = document::import_module_at(content, position) {
+        return find_module(ide, workspace_root, &module).await;
+    }
+
+    let Some((_, info)) = references::resolve_declaration(ide, content, position).await? else {
+        return Ok(None);
+    };
+
+    Ok(info
+        .defined_at
+        .as_ref()
+        .and_then(|span| span_to_location(span, workspace_root)))
+}
+
+/// Find the source file of a module
+///
+/// The IDE server has no direct lookup for module files, so this takes the
+/// `definedAt` file of any declaration from that module.
+async fn find_module(
+    ide: &IdeClient,
+    workspace_root: &str,
+    module: &str,
+) -> Result<Option<Location>> {
+    let results =
+        ide_commands::complete(ide, &[Filter::Modules(vec![module.to_string()])], None, 10).await?;
+
+    Ok(results
+        .iter()
+        .filter(|info| info.module == module)
+        .filter_map(|info| info.defined_at.as_ref())
+        .find_map(|span| {
+            let uri = build::file_path_to_uri(&span.name, workspace_root)?;
+            Some(Location {
+                uri,
+                range: Range::default(),
+            })
+        }))
+}
+
+/// Convert an IDE server span (1-based lines and columns) to an LSP location
+pub fn span_to_location(span: &ErrorSpan, workspace_root: &str) -> Option<Location> {
+    let uri = build::file_path_to_uri(&span.name, workspace_root)?;
+
+    Some(Location {
+        uri,
+        range: Range {
+            start: Position {
+                line: span.start[0].saturating_sub(1),
+                character: span.start[1].saturating_sub(1),
+            },
+            end: Position {
+                line: span.end[0].saturating_sub(1),
+                character: span.end[1].saturating_sub(1),
+            },
+        },
+    })
+}
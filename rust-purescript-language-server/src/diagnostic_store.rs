@@ -0,0 +1,371 @@
+use crate::ide_server::RebuildError;
+use std::collections::{HashMap, HashSet};
+use tower_lsp::lsp_types::Url;
+
+/// Where a set of compiler errors and warnings came from
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DiagnosticSource {
+    /// A full project build through ragu
+    Build,
+    /// A fast rebuild of a single module through the IDE server
+    Rebuild,
+}
+
+/// The errors one source reported for a file, and when
+#[derive(Debug, Clone)]
+struct Entry {
+    generation: u64,
+    errors: Vec<RebuildError>,
+}
+
+/// Compiler errors and warnings of every file, kept per source
+///
+/// Every build, rebuild and edit takes a generation number when it starts, so
+/// results that arrive out of order never replace newer ones. A file shows the
+/// merged, deduplicated results of every source that compiled its current content.
+/// Once a source compiles a file after it was edited, what other sources reported
+/// before the edit is stale and dropped; a full build covers every file, so it drops
+/// every older result of the other sources.
+#[derive(Debug, Default)]
+pub struct DiagnosticStore {
+    entries: HashMap<Url, HashMap<DiagnosticSource, Entry>>,
+    /// Generation of the latest edit of each file
+    edits: HashMap<Url, u64>,
+    /// Newest generation of each source that replaces the results of all files
+    source_generations: HashMap<DiagnosticSource, u64>,
+    generation: u64,
+}
+
+impl DiagnosticStore {
+    /// Take the generation number for a build or rebuild that is about to start
+    pub fn next_generation(&mut self) -> u64 {
+        self.generation += 1;
+        self.generation
+    }
+
+    /// Note that a file's content changed, so results compiled before are outdated
+    /// as soon as it is compiled again
+    pub fn content_changed(&mut self, uri: &Url) {
+        let generation = self.next_generation();
+        self.edits.insert(uri.clone(), generation);
+    }
+
+    /// Store the errors a source reported for one file
+    ///
+    /// Returns false (and stores nothing) if newer results are already stored, from
+    /// the same source or from another one that compiled a later edit.
+    pub fn set(
+        &mut self,
+        uri: &Url,
+        source: DiagnosticSource,
+        generation: u64,
+        errors: Vec<RebuildError>,
+    ) -> bool {
+        let edit = self.edits.get(uri).copied().unwrap_or(0);
+        let sources = self.entries.entry(uri.clone()).or_default();
+        let stale = sources.iter().any(|(other, entry)| {
+            if *other == source {
+                entry.generation > generation
+            } else {
+                generation < edit && entry.generation > edit
+            }
+        });
+        if stale {
+            return false;
+        }
+
+        // Results of other sources from before the last edit are outdated now
+        if edit < generation {
+            sources.retain(|other, entry| *other == source || entry.generation > edit);
+        }
+        sources.insert(source, Entry { generation, errors });
+        true
+    }
+
+    /// Replace everything a source reported with the results of one run over all files
+    ///
+    /// Results of other sources that started before the run are dropped too, as the
+    /// run compiled every file after them. Returns the files whose view changed,
+    /// including files that no longer have any results. Returns nothing if a newer
+    /// run was already stored.
+    pub fn replace_source(
+        &mut self,
+        source: DiagnosticSource,
+        generation: u64,
+        results: HashMap<Url, Vec<RebuildError>>,
+    ) -> Vec<Url> {
+        if self
+            .source_generations
+            .get(&source)
+            .is_some_and(|newest| *newest > generation)
+        {
+            return vec![];
+        }
+        self.source_generations.insert(source, generation);
+
+        let mut changed: HashSet<Url> = HashSet::new();
+        for (uri, sources) in &mut self.entries {
+            let before = sources.len();
+            sources.retain(|other, entry| *other != source && entry.generation > generation);
+            if sources.len() != before {
+                changed.insert(uri.clone());
+            }
+        }
+        for (uri, errors) in results {
+            if self.set(&uri, source, generation, errors) {
+                changed.insert(uri);
+            }
+        }
+        self.entries.retain(|_, sources| !sources.is_empty());
+
+        changed.into_iter().collect()
+    }
+
+    /// Forget what a source reported for one file
+    pub fn remove(&mut self, uri: &Url, source: DiagnosticSource) {
+        if let Some(sources) = self.entries.get_mut(uri) {
+            sources.remove(&source);
+            if sources.is_empty() {
+                self.entries.remove(uri);
+            }
+        }
+    }
+
+    /// Files that have errors or warnings from any source
+    pub fn files(&self) -> Vec<Url> {
+        self.entries.keys().cloned().collect()
+    }
+
+    /// Get the errors and warnings of a file, merged across sources
+    ///
+    /// Sources are merged newest first, and an error or warning reported by several
+    /// of them at the same position is only included once.
+    pub fn errors(&self, uri: &Url) -> Vec<RebuildError> {
+        let Some(sources) = self.entries.get(uri) else {
+            return vec![];
+        };
+
+        let mut entries: Vec<&Entry> = sources.values().collect();
+        entries.sort_by_key(|entry| std::cmp::Reverse(entry.generation));
+
+        let mut merged: Vec<RebuildError> = vec![];
+        for error in entries.into_iter().flat_map(|entry| &entry.errors) {
+            let duplicate = merged.iter().any(|seen| {
+                seen.error_code == error.error_code
+                    && seen.position == error.position
+                    && seen.message == error.message
+            });
+            if !duplicate {
+                merged.push(error.clone());
+            }
+        }
+        merged
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn uri(name: &str) -> Url {
+        Url::parse(&format!("file:///project/src/{}.purs", name)).unwrap()
+    }
+
+    fn error(code: &str) -> RebuildError {
+        serde_json::from_value(json!({
+            "allSpans": [],
+            "errorCode": code,
+            "errorLink": null,
+            "filename": "src/Main.purs",
+            "message": code,
+            "moduleName": "Main",
+            "position": { "startLine": 1, "startColumn": 1, "endLine": 1, "endColumn": 5 },
+            "suggestion": null
+        }))
+        .unwrap()
+    }
+
+    fn codes(store: &DiagnosticStore, uri: &Url) -> Vec<String> {
+        store
+            .errors(uri)
+            .into_iter()
+            .map(|error| error.error_code)
+            .collect()
+    }
+
+    #[test]
+    fn results_from_before_an_edit_are_dropped() {
+        let mut store = DiagnosticStore::default();
+        let main = uri("Main");
+
+        let build = store.next_generation();
+        store.content_changed(&main);
+        let rebuild = store.next_generation();
+        assert!(store.set(
+            &main,
+            DiagnosticSource::Build,
+            build,
+            vec![error("UnusedImport"), error("TypesDoNotUnify")]
+        ));
+        assert!(store.set(
+            &main,
+            DiagnosticSource::Rebuild,
+            rebuild,
+            vec![error("ShadowedName")]
+        ));
+
+        // The build's warning is as stale as its error once the edit was rebuilt
+        assert_eq!(codes(&store, &main), vec!["ShadowedName"]);
+
+        store.remove(&main, DiagnosticSource::Rebuild);
+        assert!(store.files().is_empty());
+    }
+
+    #[test]
+    fn sources_of_the_same_content_are_merged() {
+        let mut store = DiagnosticStore::default();
+        let main = uri("Main");
+
+        let build = store.next_generation();
+        let rebuild = store.next_generation();
+        store.set(
+            &main,
+            DiagnosticSource::Build,
+            build,
+            vec![error("UnusedImport"), error("MissingTypeDeclaration")],
+        );
+        store.set(
+            &main,
+            DiagnosticSource::Rebuild,
+            rebuild,
+            vec![error("ShadowedName"), error("UnusedImport")],
+        );
+
+        assert_eq!(
+            codes(&store, &main),
+            vec!["ShadowedName", "UnusedImport", "MissingTypeDeclaration"]
+        );
+    }
+
+    #[test]
+    fn clean_build_clears_older_rebuild_errors() {
+        let mut store = DiagnosticStore::default();
+        let main = uri("Main");
+
+        let rebuild = store.next_generation();
+        store.set(
+            &main,
+            DiagnosticSource::Rebuild,
+            rebuild,
+            vec![error("TypesDoNotUnify")],
+        );
+
+        let build = store.next_generation();
+        let changed = store.replace_source(DiagnosticSource::Build, build, HashMap::new());
+        assert_eq!(changed, vec![main.clone()]);
+        assert!(store.errors(&main).is_empty());
+        assert!(store.files().is_empty());
+    }
+
+    #[test]
+    fn results_arriving_out_of_order() {
+        let mut store = DiagnosticStore::default();
+        let main = uri("Main");
+
+        let build = store.next_generation();
+        store.content_changed(&main);
+        let older_rebuild = store.next_generation();
+        let newer_rebuild = store.next_generation();
+
+        assert!(store.set(
+            &main,
+            DiagnosticSource::Rebuild,
+            newer_rebuild,
+            vec![error("ShadowedName")]
+        ));
+        assert!(!store.set(
+            &main,
+            DiagnosticSource::Rebuild,
+            older_rebuild,
+            vec![error("TypesDoNotUnify")]
+        ));
+        // A build that started before the edit is outdated by the rebuild
+        assert!(!store.set(
+            &main,
+            DiagnosticSource::Build,
+            build,
+            vec![error("UnusedImport")]
+        ));
+
+        assert_eq!(codes(&store, &main), vec!["ShadowedName"]);
+    }
+
+    #[test]
+    fn replace_source_rejects_older_runs() {
+        let mut store = DiagnosticStore::default();
+        let (main, other) = (uri("Main"), uri("Other"));
+
+        let older = store.next_generation();
+        let newer = store.next_generation();
+
+        let mut changed = store.replace_source(
+            DiagnosticSource::Build,
+            newer,
+            HashMap::from([(main.clone(), vec![error("UnusedImport")])]),
+        );
+        assert_eq!(changed, vec![main.clone()]);
+
+        assert!(
+            store
+                .replace_source(
+                    DiagnosticSource::Build,
+                    older,
+                    HashMap::from([(other.clone(), vec![error("TypesDoNotUnify")])]),
+                )
+                .is_empty()
+        );
+        assert_eq!(codes(&store, &main), vec!["UnusedImport"]);
+        assert!(store.errors(&other).is_empty());
+
+        // A newer run reports files that no longer have results
+        let newest = store.next_generation();
+        changed = store.replace_source(
+            DiagnosticSource::Build,
+            newest,
+            HashMap::from([(other.clone(), vec![error("TypesDoNotUnify")])]),
+        );
+        changed.sort();
+        assert_eq!(changed, vec![main.clone(), other.clone()]);
+        assert!(store.errors(&main).is_empty());
+        assert_eq!(store.files(), vec![other]);
+    }
+
+    #[test]
+    fn removing_newest_results_falls_back() {
+        let mut store = DiagnosticStore::default();
+        let main = uri("Main");
+
+        let build = store.next_generation();
+        let rebuild = store.next_generation();
+        store.set(
+            &main,
+            DiagnosticSource::Build,
+            build,
+            vec![error("UnusedImport")],
+        );
+        store.set(
+            &main,
+            DiagnosticSource::Rebuild,
+            rebuild,
+            vec![error("ShadowedName")],
+        );
+
+        store.remove(&main, DiagnosticSource::Rebuild);
+        assert_eq!(codes(&store, &main), vec!["UnusedImport"]);
+
+        store.remove(&main, DiagnosticSource::Build);
+        assert!(store.errors(&main).is_empty());
+        assert!(store.files().is_empty());
+    }
+}
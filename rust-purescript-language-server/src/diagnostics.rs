@@ -1,8 +1,16 @@
-use crate::ide_server::RebuildError;
-use lsp_types::{Diagnostic, DiagnosticSeverity, Position, Range, Url};
+use crate::build;
+use crate::config::{DiagnosticsConfig, SeverityOverride};
+use crate::ide_server::{ErrorSpan, RebuildError};
+use crate::types::ServerState;
+use lsp_types::{
+    CodeDescription, Diagnostic, DiagnosticRelatedInformation, DiagnosticSeverity, DiagnosticTag,
+    Location, NumberOrString, Position, Range, Url,
+};
+use tokio::sync::Mutex;
+use tower_lsp::Client;
 
 /// Convert a rebuild error to an LSP diagnostic
-pub fn rebuild_error_to_diagnostic(error: &RebuildError, _uri: &Url) -> Option<Diagnostic> {
+pub fn rebuild_error_to_diagnostic(error: &RebuildError, uri: &Url) -> Option<Diagnostic> {
     let position = &error.position;
 
     let range = Range {
@@ -16,53 +24,444 @@ pub fn rebuild_error_to_diagnostic(error: &RebuildError, _uri: &Url) -> Option<D
         },
     };
 
-    let severity = match error.error_code.as_str() {
-        // Type declaration warnings - these should be warnings, not errors
-        "MissingTypeDeclaration"
-        | "ImplicitImport"
-        | "DeprecatedQualifiedSyntax"
-        | "RedundantUnqualifiedImport"
-        | "RedundantEmptyHidingImport"
-        | "DuplicateImport"
-        | "UnusedImport"
-        | "UnusedExplicitImport"
-        | "ShadowedName"
-        | "UnusedTypeVar"
-        | "UnusedName"
-        | "WildcardInferredType"
-        | "UnusedDctorImport"
-        | "UnusedDeclaration"
-        | "UserDefinedWarning"
-        | "DuplicateSelectiveImport"
-        | "ImplicitQualifiedImport"
-        | "UnnecessaryFFIModule"
-        | "MissingKindDeclaration"
-        | "DuplicateImportRef"
-        | "OverlappingPattern"
-        | "ScopeShadowing"
-        | "Deprecated" => DiagnosticSeverity::WARNING,
-
-        // Default to error for everything else
-        _ => DiagnosticSeverity::ERROR,
+    let severity = if is_warning(&error.error_code) {
+        DiagnosticSeverity::WARNING
+    } else {
+        DiagnosticSeverity::ERROR
     };
 
     Some(Diagnostic {
         range,
         severity: Some(severity),
-        code: Some(lsp_types::NumberOrString::String(error.error_code.clone())),
+        code: Some(NumberOrString::String(error.error_code.clone())),
         source: Some("purescript".to_string()),
         message: error.message.clone(),
-        related_information: None,
-        tags: None,
-        code_description: None,
+        related_information: related_information(error, uri, &range),
+        tags: diagnostic_tags(&error.error_code),
+        code_description: error
+            .error_link
+            .as_deref()
+            .and_then(|link| Url::parse(link).ok())
+            .map(|href| CodeDescription { href }),
         data: None,
     })
 }
 
-/// Convert rebuild errors to LSP diagnostics
-pub fn convert_rebuild_errors(errors: &[RebuildError], uri: &Url) -> Vec<Diagnostic> {
+/// Check if the compiler reports an error code as a warning rather than an error
+pub fn is_warning(error_code: &str) -> bool {
+    matches!(
+        error_code,
+        "MissingTypeDeclaration"
+            | "ImplicitImport"
+            | "DeprecatedQualifiedSyntax"
+            | "RedundantUnqualifiedImport"
+            | "RedundantEmptyHidingImport"
+            | "DuplicateImport"
+            | "UnusedImport"
+            | "UnusedExplicitImport"
+            | "ShadowedName"
+            | "UnusedTypeVar"
+            | "UnusedName"
+            | "WildcardInferredType"
+            | "UnusedDctorImport"
+            | "UnusedDeclaration"
+            | "UserDefinedWarning"
+            | "DuplicateSelectiveImport"
+            | "ImplicitQualifiedImport"
+            | "UnnecessaryFFIModule"
+            | "MissingKindDeclaration"
+            | "DuplicateImportRef"
+            | "OverlappingPattern"
+            | "ScopeShadowing"
+            | "Deprecated"
+    )
+}
+
+/// Tag unused and deprecated code so editors can fade it out or strike it through
+fn diagnostic_tags(error_code: &str) -> Option<Vec<DiagnosticTag>> {
+    match error_code {
+        "UnusedImport"
+        | "UnusedExplicitImport"
+        | "UnusedDctorImport"
+        | "UnusedDctorExplicitImport"
+        | "UnusedName"
+        | "UnusedDeclaration"
+        | "UnusedTypeVar"
+        | "UnusedFFIImplementations"
+        | "RedundantEmptyHidingImport"
+        | "RedundantUnqualifiedImport"
+        | "DuplicateImport"
+        | "DuplicateImportRef"
+        | "DuplicateSelectiveImport"
+        | "UnnecessaryFFIModule" => Some(vec![DiagnosticTag::UNNECESSARY]),
+        "Deprecated" | "DeprecatedQualifiedSyntax" => Some(vec![DiagnosticTag::DEPRECATED]),
+        _ => None,
+    }
+}
+
+/// Point at the other source spans involved in an error (e.g. both sides of a conflict)
+fn related_information(
+    error: &RebuildError,
+    uri: &Url,
+    range: &Range,
+) -> Option<Vec<DiagnosticRelatedInformation>> {
+    let message = error
+        .message
+        .lines()
+        .map(str::trim)
+        .find(|line| !line.is_empty())
+        .unwrap_or("Related location")
+        .to_string();
+
+    let related: Vec<DiagnosticRelatedInformation> = error
+        .all_spans
+        .iter()
+        .flatten()
+        .filter_map(|span| {
+            let location = Location {
+                uri: span_uri(span, error, uri)?,
+                range: Range {
+                    start: Position {
+                        line: span.start[0].saturating_sub(1),
+                        character: span.start[1].saturating_sub(1),
+                    },
+                    end: Position {
+                        line: span.end[0].saturating_sub(1),
+                        character: span.end[1].saturating_sub(1),
+                    },
+                },
+            };
+            // The primary span is the diagnostic itself
+            (location.uri != *uri || location.range != *range).then(|| {
+                DiagnosticRelatedInformation {
+                    location,
+                    message: message.clone(),
+                }
+            })
+        })
+        .collect();
+
+    (!related.is_empty()).then_some(related)
+}
+
+/// Resolve a span's file, relative to the workspace root the error's own file is in
+fn span_uri(span: &ErrorSpan, error: &RebuildError, uri: &Url) -> Option<Url> {
+    if span.name == error.filename {
+        return Some(uri.clone());
+    }
+    if std::path::Path::new(&span.name).is_absolute() {
+        return build::file_path_to_uri(&span.name, "");
+    }
+
+    let file_path = uri.to_file_path().ok()?;
+    let workspace_root = file_path.to_str()?.strip_suffix(&error.filename)?;
+    build::file_path_to_uri(&span.name, workspace_root)
+}
+
+/// Convert rebuild errors to LSP diagnostics, applying the configured censoring and
+/// severity overrides
+pub fn convert_rebuild_errors(
+    errors: &[RebuildError],
+    uri: &Url,
+    config: &DiagnosticsConfig,
+) -> Vec<Diagnostic> {
+    let absolute_path = uri.to_file_path().unwrap_or_default();
+
     errors
         .iter()
+        .filter(|error| !config.is_censored(&error.error_code, &error.filename, &absolute_path))
         .filter_map(|error| rebuild_error_to_diagnostic(error, uri))
+        .filter(|diagnostic| {
+            // Project censoring only applies to warnings, like the command line build
+            diagnostic.severity != Some(DiagnosticSeverity::WARNING)
+                || !matches!(&diagnostic.code, Some(NumberOrString::String(code))
+                    if config.project_censoring.censors_warning(code))
+        })
+        .map(|mut diagnostic| {
+            let override_severity = diagnostic.code.as_ref().and_then(|code| match code {
+                NumberOrString::String(code) => config.severity_overrides.get(code),
+                NumberOrString::Number(_) => None,
+            });
+            diagnostic.severity = match override_severity {
+                Some(SeverityOverride::Error) => Some(DiagnosticSeverity::ERROR),
+                Some(SeverityOverride::Warning) => Some(DiagnosticSeverity::WARNING),
+                Some(SeverityOverride::Info) => Some(DiagnosticSeverity::INFORMATION),
+                Some(SeverityOverride::Hint) => Some(DiagnosticSeverity::HINT),
+                Some(SeverityOverride::Off) | None => diagnostic.severity,
+            };
+            diagnostic
+        })
         .collect()
 }
+
+/// Publish the merged diagnostics of a file from the diagnostic store
+pub async fn publish(client: &Client, state: &Mutex<ServerState>, uri: &Url) {
+    let (errors, config) = {
+        let state = state.lock().await;
+        (state.diagnostics.errors(uri), state.diagnostics_config())
+    };
+
+    client
+        .publish_diagnostics(
+            uri.clone(),
+            convert_rebuild_errors(&errors, uri, &config),
+            None,
+        )
+        .await;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::ProjectCensoring;
+    use serde_json::json;
+    use std::collections::HashMap;
+
+    fn uri() -> Url {
+        Url::parse("file:///project/src/Main.purs").unwrap()
+    }
+
+    fn span(name: &str, start: [u32; 2], end: [u32; 2]) -> serde_json::Value {
+        json!({ "name": name, "start": start, "end": end })
+    }
+
+    fn error(code: &str, link: Option<&str>, all_spans: serde_json::Value) -> RebuildError {
+        serde_json::from_value(json!({
+            "allSpans": all_spans,
+            "errorCode": code,
+            "errorLink": link,
+            "filename": "src/Main.purs",
+            "message": "  The import of Data.Maybe is redundant\n\n",
+            "moduleName": "Main",
+            "position": { "startLine": 3, "startColumn": 1, "endLine": 3, "endColumn": 21 },
+            "suggestion": null
+        }))
+        .unwrap()
+    }
+
+    fn range(start: (u32, u32), end: (u32, u32)) -> Range {
+        Range {
+            start: Position {
+                line: start.0,
+                character: start.1,
+            },
+            end: Position {
+                line: end.0,
+                character: end.1,
+            },
+        }
+    }
+
+    #[test]
+    fn warning_with_related_spans() {
+        let link = "https://github.com/purescript/documentation/blob/master/errors/UnusedImport.md";
+        let error = error(
+            "UnusedImport",
+            Some(link),
+            json!([
+                span("src/Main.purs", [3, 1], [3, 21]),
+                span("src/Main.purs", [7, 5], [7, 9]),
+                span("src/Other.purs", [5, 1], [5, 10])
+            ]),
+        );
+
+        let diagnostic = rebuild_error_to_diagnostic(&error, &uri()).unwrap();
+        assert_eq!(diagnostic.range, range((2, 0), (2, 20)));
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(
+            diagnostic.code,
+            Some(NumberOrString::String("UnusedImport".to_string()))
+        );
+        assert_eq!(diagnostic.source.as_deref(), Some("purescript"));
+        assert_eq!(
+            diagnostic
+                .code_description
+                .map(|description| description.href.to_string()),
+            Some(link.to_string())
+        );
+        assert_eq!(diagnostic.tags, Some(vec![DiagnosticTag::UNNECESSARY]));
+
+        // The primary span is left out
+        let related = diagnostic.related_information.unwrap();
+        assert_eq!(
+            related
+                .iter()
+                .map(|info| (info.location.uri.to_string(), info.location.range))
+                .collect::<Vec<_>>(),
+            vec![
+                (uri().to_string(), range((6, 4), (6, 8))),
+                (
+                    "file:///project/src/Other.purs".to_string(),
+                    range((4, 0), (4, 9))
+                ),
+            ]
+        );
+        assert!(
+            related
+                .iter()
+                .all(|info| info.message == "The import of Data.Maybe is redundant")
+        );
+    }
+
+    #[test]
+    fn error_without_link_or_related_spans() {
+        let error = error(
+            "TypesDoNotUnify",
+            None,
+            json!([span("src/Main.purs", [3, 1], [3, 21])]),
+        );
+
+        let diagnostic = rebuild_error_to_diagnostic(&error, &uri()).unwrap();
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::ERROR));
+        assert_eq!(diagnostic.code_description, None);
+        assert_eq!(diagnostic.related_information, None);
+        assert_eq!(diagnostic.tags, None);
+    }
+
+    #[test]
+    fn deprecated_code_is_tagged() {
+        let error = error("DeprecatedQualifiedSyntax", None, json!(null));
+
+        let diagnostic = rebuild_error_to_diagnostic(&error, &uri()).unwrap();
+        assert_eq!(diagnostic.severity, Some(DiagnosticSeverity::WARNING));
+        assert_eq!(diagnostic.tags, Some(vec![DiagnosticTag::DEPRECATED]));
+    }
+
+    fn codes_and_severities(
+        codes: &[&str],
+        config: &DiagnosticsConfig,
+    ) -> Vec<(String, Option<DiagnosticSeverity>)> {
+        let errors: Vec<RebuildError> = codes
+            .iter()
+            .map(|code| error(code, None, json!(null)))
+            .collect();
+
+        convert_rebuild_errors(&errors, &uri(), config)
+            .into_iter()
+            .map(|diagnostic| match diagnostic.code {
+                Some(NumberOrString::String(code)) => (code, diagnostic.severity),
+                _ => panic!("Expected a string code"),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn severity_overrides() {
+        let config = DiagnosticsConfig {
+            severity_overrides: HashMap::from([
+                (
+                    "MissingTypeDeclaration".to_string(),
+                    SeverityOverride::Error,
+                ),
+                ("TypesDoNotUnify".to_string(), SeverityOverride::Hint),
+                ("ShadowedName".to_string(), SeverityOverride::Off),
+            ]),
+            ..Default::default()
+        };
+
+        assert_eq!(
+            codes_and_severities(
+                &[
+                    "MissingTypeDeclaration",
+                    "TypesDoNotUnify",
+                    "ShadowedName",
+                    "UnusedImport"
+                ],
+                &config
+            ),
+            vec![
+                (
+                    "MissingTypeDeclaration".to_string(),
+                    Some(DiagnosticSeverity::ERROR)
+                ),
+                (
+                    "TypesDoNotUnify".to_string(),
+                    Some(DiagnosticSeverity::HINT)
+                ),
+                (
+                    "UnusedImport".to_string(),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+            ]
+        );
+    }
+
+    #[test]
+    fn censored_codes() {
+        let config = DiagnosticsConfig {
+            censor_codes: vec!["UnusedImport".to_string()],
+            ..Default::default()
+        };
+
+        assert_eq!(
+            codes_and_severities(&["UnusedImport", "ShadowedName"], &config),
+            vec![(
+                "ShadowedName".to_string(),
+                Some(DiagnosticSeverity::WARNING)
+            )]
+        );
+    }
+
+    #[test]
+    fn censored_files() {
+        let censor_files = |globs: &[&str]| DiagnosticsConfig {
+            censor_files: globs.iter().map(|glob| glob.to_string()).collect(),
+            ..Default::default()
+        };
+        let codes = ["UnusedImport", "TypesDoNotUnify"];
+
+        // Relative to the workspace root, as the compiler reports them, or absolute
+        assert!(codes_and_severities(&codes, &censor_files(&["src/*.purs"])).is_empty());
+        assert!(codes_and_severities(&codes, &censor_files(&["/project/src/**"])).is_empty());
+        assert_eq!(
+            codes_and_severities(&codes, &censor_files(&["src/Generated/**"])).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn project_censoring_only_hides_warnings() {
+        let config = DiagnosticsConfig {
+            project_censoring: ProjectCensoring {
+                all: false,
+                codes: vec!["ShadowedName".to_string()],
+                prefixes: vec!["Unused".to_string(), "Unknown".to_string()],
+            },
+            ..Default::default()
+        };
+
+        assert_eq!(
+            codes_and_severities(
+                &[
+                    "ShadowedName",
+                    "UnusedImport",
+                    "UnknownName",
+                    "MissingTypeDeclaration"
+                ],
+                &config
+            ),
+            vec![
+                ("UnknownName".to_string(), Some(DiagnosticSeverity::ERROR)),
+                (
+                    "MissingTypeDeclaration".to_string(),
+                    Some(DiagnosticSeverity::WARNING)
+                ),
+            ]
+        );
+
+        let config = DiagnosticsConfig {
+            project_censoring: ProjectCensoring {
+                all: true,
+                ..Default::default()
+            },
+            ..Default::default()
+        };
+        assert_eq!(
+            codes_and_severities(&["UnusedImport", "TypesDoNotUnify"], &config),
+            vec![(
+                "TypesDoNotUnify".to_string(),
+                Some(DiagnosticSeverity::ERROR)
+            )]
+        );
+    }
+}
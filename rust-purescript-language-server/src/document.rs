@@ -0,0 +1,985 @@
+use lsp_types::{Position, Range};
+
+/// An identifier or operator found in a document, with its optional module qualifier
+#[derive(Debug, Clone, PartialEq)]
+pub struct Identifier {
+    pub qualifier: Option<String>,
+    pub name: String,
+    pub range: Range,
+}
+
+/// An import declaration parsed from the module header
+#[derive(Debug, Clone, PartialEq)]
+pub struct Import {
+    pub module: String,
+    pub qualifier: Option<String>,
+    /// 0-based line the import starts on
+    pub start_line: u32,
+    /// 0-based line the import ends on (imports can span several lines)
+    pub end_line: u32,
+}
+
+fn is_identifier_char(c: char) -> bool {
+    c.is_alphanumeric() || c == '_' || c == '\''
+}
+
+pub fn is_operator_char(c: char) -> bool {
+    ":!#$%&*+./<=>?@\\^|-~".contains(c)
+}
+
+/// Get a line of the document as a vector of chars (LSP columns are treated as char offsets)
+fn line_chars(text: &str, line: u32) -> Option<Vec<char>> {
+    text.lines()
+        .nth(line as usize)
+        .map(|line| line.chars().collect())
+}
+
+/// Find the identifier (or operator) under the cursor, including any module qualifier
+///
+/// `Map.insert` yields qualifier `Map` and name `insert`; hovering over the
+/// qualifier itself yields the same result.
+pub fn identifier_at(text: &str, position: Position) -> Option<Identifier> {
+    let chars = line_chars(text, position.line)?;
+    let mut cursor = (position.character as usize).min(chars.len());
+
+    // Allow the cursor to sit just after the word (e.g. at the end of a line)
+    if cursor == chars.len()
+        || !is_identifier_char(chars[cursor]) && !is_operator_char(chars[cursor])
+    {
+        if cursor > 0
+            && (is_identifier_char(chars[cursor - 1]) || is_operator_char(chars[cursor - 1]))
+        {
+            cursor -= 1;
+        } else {
+            return None;
+        }
+    }
+
+    if is_operator_char(chars[cursor]) && chars[cursor] != '.' {
+        let mut start = cursor;
+        while start > 0 && is_operator_char(chars[start - 1]) {
+            start -= 1;
+        }
+        let mut end = cursor;
+        while end < chars.len() && is_operator_char(chars[end]) {
+            end += 1;
+        }
+        return Some(Identifier {
+            qualifier: None,
+            name: chars[start..end].iter().collect(),
+            range: line_range(position.line, start, end),
+        });
+    }
+
+    // Expand over a dotted name such as `Data.Map.insert`
+    let mut start = cursor;
+    while start > 0 && (is_identifier_char(chars[start - 1]) || chars[start - 1] == '.') {
+        start -= 1;
+    }
+    let mut end = cursor;
+    while end < chars.len() && (is_identifier_char(chars[end]) || chars[end] == '.') {
+        end += 1;
+    }
+
+    let dotted: String = chars[start..end].iter().collect();
+    let dotted = dotted.trim_matches('.');
+    let segments: Vec<&str> = dotted.split('.').filter(|s| !s.is_empty()).collect();
+    let (name, qualifier_segments) = segments.split_last()?;
+
+    // Only leading uppercase segments form a qualifier (`rec.field` is a record access)
+    if qualifier_segments
+        .iter()
+        .any(|segment| !segment.starts_with(|c: char| c.is_uppercase()))
+    {
+        let word_start = start
+            + chars[start..cursor]
+                .iter()
+                .rposition(|c| *c == '.')
+                .map(|i| i + 1)
+                .unwrap_or(0);
+        let word_end = cursor
+            + chars[cursor..end]
+                .iter()
+                .position(|c| *c == '.')
+                .unwrap_or(end - cursor);
+        return Some(Identifier {
+            qualifier: None,
+            name: chars[word_start..word_end].iter().collect(),
+            range: line_range(position.line, word_start, word_end),
+        });
+    }
+
+    let name_start = end - name.chars().count();
+    Some(Identifier {
+        qualifier: if qualifier_segments.is_empty() {
+            None
+        } else {
+            Some(qualifier_segments.join("."))
+        },
+        name: name.to_string(),
+        range: line_range(position.line, name_start, end),
+    })
+}
+
+fn line_range(line: u32, start: usize, end: usize) -> Range {
+    Range {
+        start: Position {
+            line,
+            character: start as u32,
+        },
+        end: Position {
+            line,
+            character: end as u32,
+        },
+    }
+}
+
+/// Get the module name declared in the module header
+pub fn module_name(text: &str) -> Option<String> {
+    let re = regex::Regex::new(r"(?m)^module\s+([A-Z][A-Za-z0-9_'.]*)").unwrap();
+    re.captures(text).map(|captures| captures[1].to_string())
+}
+
+/// Parse the import declarations of a module
+pub fn parse_imports(text: &str) -> Vec<Import> {
+    let import_re = regex::Regex::new(r"^import\s+([A-Z][A-Za-z0-9_'.]*)").unwrap();
+    let qualifier_re = regex::Regex::new(r"\bas\s+([A-Z][A-Za-z0-9_'.]*)\s*$").unwrap();
+
+    let lines: Vec<&str> = text.lines().collect();
+    let mut imports = Vec::new();
+    let mut index = 0;
+
+    while index < lines.len() {
+        let Some(captures) = import_re.captures(lines[index]) else {
+            index += 1;
+            continue;
+        };
+
+        // Continuation lines of a multi-line import are indented
+        let start_line = index;
+        let mut declaration = lines[index].to_string();
+        while index + 1 < lines.len()
+            && lines[index + 1].starts_with(char::is_whitespace)
+            && !lines[index + 1].trim().is_empty()
+        {
+            index += 1;
+            declaration.push(' ');
+            declaration.push_str(lines[index].trim());
+        }
+
+        let declaration = strip_line_comment(&declaration);
+        imports.push(Import {
+            module: captures[1].to_string(),
+            qualifier: qualifier_re
+                .captures(declaration.trim_end())
+                .map(|captures| captures[1].to_string()),
+            start_line: start_line as u32,
+            end_line: index as u32,
+        });
+
+        index += 1;
+    }
+
+    imports
+}
+
+fn strip_line_comment(line: &str) -> &str {
+    line.find("--").map(|i| &line[..i]).unwrap_or(line)
+}
+
+/// Get the modules imported under the given qualifier (several imports can share one alias)
+pub fn modules_for_qualifier(imports: &[Import], qualifier: &str) -> Vec<String> {
+    imports
+        .iter()
+        .filter(|import| import.qualifier.as_deref() == Some(qualifier))
+        .map(|import| import.module.clone())
+        .collect()
+}
+
+/// Get the module header (everything up to and including the last import)
+///
+/// This is what the IDE server's `dependencies` filter expects as `moduleText`.
+pub fn module_header(text: &str) -> String {
+    let last_line = match parse_imports(text).last() {
+        Some(import) => import.end_line as usize,
+        // Without imports the header ends with the `where` of the module declaration
+        None => text
+            .lines()
+            .position(|line| line.trim_end().ends_with("where"))
+            .unwrap_or(0),
+    };
+
+    text.lines()
+        .take(last_line + 1)
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// The partially typed word before the cursor, used as a completion prefix
+#[derive(Debug, Clone, PartialEq)]
+pub struct CompletionPrefix {
+    pub qualifier: Option<String>,
+    pub prefix: String,
+    pub range: Range,
+}
+
+/// Find the word being typed before the cursor, including any module qualifier
+///
+/// `Map.ins|` yields qualifier `Map` and prefix `ins`; `Map.|` yields an empty prefix.
+pub fn completion_prefix_at(text: &str, position: Position) -> Option<CompletionPrefix> {
+    let chars = line_chars(text, position.line).unwrap_or_default();
+    let cursor = (position.character as usize).min(chars.len());
+
+    let mut start = cursor;
+    while start > 0 && is_identifier_char(chars[start - 1]) {
+        start -= 1;
+    }
+    let prefix: String = chars[start..cursor].iter().collect();
+
+    // Walk back over `Qualifier.` segments
+    let mut qualifier_start = start;
+    while qualifier_start > 1 && chars[qualifier_start - 1] == '.' {
+        let mut segment_start = qualifier_start - 1;
+        while segment_start > 0 && is_identifier_char(chars[segment_start - 1]) {
+            segment_start -= 1;
+        }
+        if segment_start == qualifier_start - 1 || !chars[segment_start].is_uppercase() {
+            break;
+        }
+        qualifier_start = segment_start;
+    }
+
+    let qualifier = if qualifier_start < start {
+        Some(chars[qualifier_start..start - 1].iter().collect::<String>())
+    } else {
+        None
+    };
+
+    if prefix.is_empty() && qualifier.is_none() {
+        return None;
+    }
+
+    Some(CompletionPrefix {
+        qualifier,
+        prefix,
+        range: line_range(position.line, start, cursor),
+    })
+}
+
+/// Get the module name under the cursor on an `import` line
+pub fn import_module_at(text: &str, position: Position) -> Option<String> {
+    let line = text.lines().nth(position.line as usize)?;
+    let re = regex::Regex::new(r"^import\s+([A-Z][A-Za-z0-9_'.]*)").unwrap();
+    let module = re.captures(line)?.get(1)?;
+
+    let start = line[..module.start()].chars().count() as u32;
+    let end = start + module.as_str().chars().count() as u32;
+
+    (start..=end)
+        .contains(&position.character)
+        .then(|| module.as_str().to_string())
+}
+
+/// Find whole-word occurrences of an identifier in a line, as char column ranges
+pub fn find_word(line: &str, word: &str) -> Vec<(u32, u32)> {
+    let chars: Vec<char> = line.chars().collect();
+    let word: Vec<char> = word.chars().collect();
+    let mut occurrences = Vec::new();
+
+    if word.is_empty() || word.len() > chars.len() {
+        return occurrences;
+    }
+
+    for start in 0..=chars.len() - word.len() {
+        let end = start + word.len();
+        if chars[start..end] == word[..]
+            && (start == 0 || !is_identifier_char(chars[start - 1]))
+            && (end == chars.len() || !is_identifier_char(chars[end]))
+        {
+            occurrences.push((start as u32, end as u32));
+        }
+    }
+
+    occurrences
+}
+
+/// A top-level function clause such as `foo (Just x) y = ...`
+#[derive(Debug, Clone, PartialEq)]
+pub struct FunctionClause {
+    pub name: String,
+    /// Argument patterns with their char column ranges
+    pub args: Vec<(String, u32, u32)>,
+}
+
+/// Parse the left-hand side of a top-level function clause
+pub fn parse_function_clause(line: &str) -> Option<FunctionClause> {
+    let chars: Vec<char> = line.chars().collect();
+    if !chars.first()?.is_lowercase() && chars[0] != '_' {
+        return None;
+    }
+
+    let mut index = 0;
+    while index < chars.len() && is_identifier_char(chars[index]) {
+        index += 1;
+    }
+    let name: String = chars[..index].iter().collect();
+
+    let mut args = Vec::new();
+    loop {
+        while index < chars.len() && chars[index].is_whitespace() {
+            index += 1;
+        }
+        if index >= chars.len() {
+            // A clause must have a right-hand side on the same line
+            return None;
+        }
+
+        let start = index;
+        match chars[index] {
+            '=' | '|' => break,
+            ':' => return None,
+            '(' | '[' | '{' => index = skip_group(&chars, index)?,
+            _ => {
+                while index < chars.len()
+                    && !chars[index].is_whitespace()
+                    && !"=|([{".contains(chars[index])
+                {
+                    index += 1;
+                }
+                // As-patterns such as `x@(Just y)`
+                if index < chars.len() && chars[index - 1] == '@' && "([{".contains(chars[index]) {
+                    index = skip_group(&chars, index)?;
+                }
+            }
+        }
+
+        args.push((
+            chars[start..index].iter().collect(),
+            start as u32,
+            index as u32,
+        ));
+    }
+
+    Some(FunctionClause { name, args })
+}
+
+/// Skip a bracketed group starting at `start`, returning the index just after it
+fn skip_group(chars: &[char], start: usize) -> Option<usize> {
+    let mut depth = 0;
+    for (index, c) in chars.iter().enumerate().skip(start) {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(index + 1);
+                }
+            }
+            _ => {}
+        }
+    }
+    None
+}
+
+/// Find the type signature of a top-level declaration, joined onto a single line
+pub fn type_signature(text: &str, name: &str) -> Option<String> {
+    let lines: Vec<&str> = text.lines().collect();
+    let signature_re =
+        regex::Regex::new(&format!(r"^{}\s*::\s*(.*)$", regex::escape(name))).unwrap();
+
+    let index = lines.iter().position(|line| signature_re.is_match(line))?;
+    let mut signature = signature_re.captures(lines[index])?[1].trim().to_string();
+
+    for line in &lines[index + 1..] {
+        if !line.starts_with(char::is_whitespace) || line.trim().is_empty() {
+            break;
+        }
+        signature.push(' ');
+        signature.push_str(line.trim());
+    }
+
+    Some(signature)
+}
+
+/// Split a function type into its argument types and result type
+///
+/// Leading `forall`s and constraints are dropped, so
+/// `forall a. Show a => a -> Maybe a -> String` yields `["a", "Maybe a", "String"]`.
+pub fn split_function_type(signature: &str) -> Vec<String> {
+    function_type_ranges(signature)
+        .into_iter()
+        .map(|range| signature[range].to_string())
+        .collect()
+}
+
+/// Byte ranges of the argument types and result type of a function type
+///
+/// The ranges point into `signature` and cover the same parts `split_function_type`
+/// returns. Only top-level constraints are dropped, so a parameter with its own
+/// constraint, as in `Show a => (forall b. Eq b => b -> a) -> String`, stays whole.
+pub fn function_type_ranges(signature: &str) -> Vec<std::ops::Range<usize>> {
+    let code = strip_line_comment(signature);
+    let mut range = trimmed_range(code, 0..code.len());
+
+    // Drop `forall a b.` quantifiers
+    while let Some(rest) = code[range.clone()].strip_prefix("forall") {
+        match rest.find('.') {
+            Some(dot) => {
+                range = trimmed_range(code, range.start + "forall".len() + dot + 1..range.end)
+            }
+            None => break,
+        }
+    }
+
+    // Drop constraints, which end at the last top-level `=>`
+    if let Some(last) = split_top_level(&code[range.clone()], "=>").pop() {
+        range = range.start + last.start..range.start + last.end;
+    }
+
+    split_top_level(&code[range.clone()], "->")
+        .into_iter()
+        .map(|part| range.start + part.start..range.start + part.end)
+        .collect()
+}
+
+/// Split a type on a separator that is not nested inside brackets, returning the
+/// byte range of each part without surrounding whitespace
+fn split_top_level(text: &str, separator: &str) -> Vec<std::ops::Range<usize>> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut part_start = 0;
+    let mut index = 0;
+
+    while let Some(c) = text[index..].chars().next() {
+        if depth == 0 && text[index..].starts_with(separator) {
+            parts.push(trimmed_range(text, part_start..index));
+            index += separator.len();
+            part_start = index;
+            continue;
+        }
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            _ => {}
+        }
+        index += c.len_utf8();
+    }
+
+    parts.push(trimmed_range(text, part_start..text.len()));
+    parts
+}
+
+/// Narrow a byte range of a text so it excludes leading and trailing whitespace
+fn trimmed_range(text: &str, range: std::ops::Range<usize>) -> std::ops::Range<usize> {
+    let part = &text[range.clone()];
+    let start = range.start + (part.len() - part.trim_start().len());
+    let end = range.start + part.trim_end().len();
+    start..end.max(start)
+}
+
+/// A function application being typed, such as `foo x (bar y) |`
+#[derive(Debug, Clone, PartialEq)]
+pub struct Application {
+    /// The function being applied
+    pub function: Identifier,
+    /// 0-based index of the argument under the cursor
+    pub argument: u32,
+}
+
+/// Words and bracketed groups of one nesting level of an expression
+#[derive(Default)]
+struct ApplicationFrame {
+    /// Words as `(text, start, end)`; literals and groups are `None`
+    atoms: Vec<Option<(String, usize, usize)>>,
+    /// Whether the frame was opened by a bracket (the outermost one is not)
+    bracketed: bool,
+}
+
+/// Keywords that start a new expression, so an application cannot span them
+const EXPRESSION_KEYWORDS: &[&str] = &[
+    "ado", "case", "do", "else", "if", "in", "let", "of", "then", "where",
+];
+
+/// Find the function application the cursor is in and the argument being typed
+///
+/// This is a lightweight parser over the current line: brackets start a nested
+/// application, while operators, `=`, `,` and keywords start a new one. After
+/// `foo ` the cursor is at argument 0; after `foo x` it is still on argument 0.
+pub fn application_at(text: &str, position: Position) -> Option<Application> {
+    let chars = line_chars(text, position.line)?;
+    let cursor = (position.character as usize).min(chars.len());
+
+    let mut frames = vec![ApplicationFrame::default()];
+    let mut index = 0;
+
+    while index < cursor {
+        let c = chars[index];
+        let frame = frames.last_mut()?;
+
+        if c.is_whitespace() {
+            index += 1;
+        } else if c.is_alphanumeric() || c == '_' {
+            let start = index;
+            while index < cursor
+                && (is_identifier_char(chars[index])
+                    || chars[index] == '.'
+                        && chars.get(index + 1).is_some_and(|c| is_identifier_char(*c)))
+            {
+                index += 1;
+            }
+            let word: String = chars[start..index].iter().collect();
+            if EXPRESSION_KEYWORDS.contains(&word.as_str()) {
+                frame.atoms.clear();
+            } else if c.is_ascii_digit() {
+                frame.atoms.push(None);
+            } else {
+                frame.atoms.push(Some((word, start, index)));
+            }
+        } else if c == '"' || c == '\'' {
+            // String and char literals
+            index += 1;
+            while index < cursor && chars[index] != c {
+                if chars[index] == '\\' {
+                    index += 1;
+                }
+                index += 1;
+            }
+            index += 1;
+            frame.atoms.push(None);
+        } else if "([{".contains(c) {
+            frame.atoms.push(None);
+            frames.push(ApplicationFrame {
+                atoms: vec![],
+                bracketed: true,
+            });
+            index += 1;
+        } else if ")]}".contains(c) {
+            if frames.len() > 1 {
+                frames.pop();
+            }
+            index += 1;
+        } else if c == '-' && chars.get(index + 1) == Some(&'-') {
+            // The cursor is inside a comment
+            return None;
+        } else {
+            // Operators, `,` and backtick infix functions end the current application
+            frame.atoms.clear();
+            index += 1;
+        }
+    }
+
+    let typing_word = cursor > 0 && !chars[cursor - 1].is_whitespace();
+
+    // Directly after an opening bracket, the group is an argument of the outer application
+    let mut argument_offset = 0;
+    let mut frame = frames.pop()?;
+    if frame.atoms.is_empty() && frame.bracketed {
+        frame = frames.pop()?;
+        argument_offset = 1;
+    }
+
+    let (word, _, end) = frame.atoms.first()?.clone()?;
+    let arguments = frame.atoms.len() - 1;
+    let argument = if argument_offset == 1 || typing_word {
+        arguments.checked_sub(1)?
+    } else {
+        arguments
+    };
+
+    // Only leading uppercase segments form a qualifier (`rec.field` is a record access)
+    let segments: Vec<&str> = word.split('.').collect();
+    let (name, qualifier_segments) = segments.split_last()?;
+    if qualifier_segments
+        .iter()
+        .any(|segment| !segment.starts_with(|c: char| c.is_uppercase()))
+    {
+        return None;
+    }
+
+    Some(Application {
+        function: Identifier {
+            qualifier: if qualifier_segments.is_empty() {
+                None
+            } else {
+                Some(qualifier_segments.join("."))
+            },
+            name: name.to_string(),
+            range: line_range(position.line, end - name.chars().count(), end),
+        },
+        argument: argument as u32,
+    })
+}
+
+/// Check whether the name at a position is in a type rather than in an expression
+///
+/// Types and data constructors often share a name, as in `newtype Name = Name String`,
+/// so this decides which namespace a capitalized name refers to. The declaration is
+/// scanned from its first (unindented) line: `::` starts a type, while `=`, `<-` and
+/// expression keywords end one. In `data` and `newtype` declarations the first name
+/// after `=` or `|` is a constructor, and in import and export lists names directly in
+/// the list are types while names inside `Type(..)` are constructors.
+pub fn is_type_position(text: &str, position: Position) -> bool {
+    let lines: Vec<&str> = text.lines().collect();
+    let line = position.line as usize;
+    let Some(current) = lines.get(line) else {
+        return false;
+    };
+
+    // Scan up to the start of the word under the cursor
+    let current: Vec<char> = current.chars().collect();
+    let mut word_start = (position.character as usize).min(current.len());
+    while word_start > 0
+        && (is_identifier_char(current[word_start - 1]) || current[word_start - 1] == '.')
+    {
+        word_start -= 1;
+    }
+
+    let start = (0..=line)
+        .rev()
+        .find(|&index| lines[index].starts_with(|c: char| !c.is_whitespace()))
+        .unwrap_or(0);
+    let mut chars: Vec<char> = Vec::new();
+    for declaration_line in &lines[start..line] {
+        chars.extend(declaration_line.chars());
+        chars.push('\n');
+    }
+    chars.extend(&current[..word_start]);
+
+    let first_word: String = chars
+        .iter()
+        .take_while(|c| is_identifier_char(**c))
+        .collect();
+    let import_list = matches!(first_word.as_str(), "import" | "module");
+    let data_declaration = matches!(first_word.as_str(), "data" | "newtype");
+    let type_declaration = data_declaration
+        || matches!(
+            first_word.as_str(),
+            "type" | "class" | "instance" | "derive" | "else"
+        )
+        || chars
+            .iter()
+            .collect::<String>()
+            .starts_with("foreign import data");
+
+    // Whether each nesting level is in a type; the outermost one is the declaration
+    let mut frames = vec![type_declaration];
+    let mut expect_constructor = false;
+    let mut index = first_word.chars().count();
+
+    while index < chars.len() {
+        let c = chars[index];
+        let top_level = frames.len() == 1;
+
+        if c.is_whitespace() {
+            index += 1;
+        } else if c == '-' && chars.get(index + 1) == Some(&'-') {
+            while index < chars.len() && chars[index] != '\n' {
+                index += 1;
+            }
+        } else if c.is_alphanumeric() || c == '_' {
+            let word_start = index;
+            while index < chars.len() && (is_identifier_char(chars[index]) || chars[index] == '.') {
+                index += 1;
+            }
+            let word: String = chars[word_start..index].iter().collect();
+            if EXPRESSION_KEYWORDS.contains(&word.as_str()) {
+                *frames.last_mut().unwrap() = false;
+            } else if expect_constructor && top_level && c.is_uppercase() {
+                // The constructor's fields are types
+                expect_constructor = false;
+                frames[0] = true;
+            }
+        } else if c == '"' || c == '\'' {
+            index += 1;
+            while index < chars.len() && chars[index] != c {
+                if chars[index] == '\\' {
+                    index += 1;
+                }
+                index += 1;
+            }
+            index += 1;
+        } else if "([{".contains(c) {
+            let in_type = *frames.last().unwrap();
+            frames.push(in_type);
+            index += 1;
+        } else if ")]}".contains(c) {
+            if frames.len() > 1 {
+                frames.pop();
+            }
+            index += 1;
+        } else if is_operator_char(c) {
+            let operator_start = index;
+            while index < chars.len() && is_operator_char(chars[index]) {
+                index += 1;
+            }
+            let operator: String = chars[operator_start..index].iter().collect();
+            let in_type = frames.last_mut().unwrap();
+            match operator.as_str() {
+                "::" => *in_type = true,
+                "=" | "<-" => {
+                    *in_type = false;
+                    expect_constructor = data_declaration && top_level;
+                }
+                "|" if data_declaration && top_level => expect_constructor = true,
+                // Guards are expressions, while rows (`| r`) are nested in brackets
+                "|" if top_level => *in_type = false,
+                _ => {}
+            }
+        } else {
+            index += 1;
+        }
+    }
+
+    if import_list {
+        return frames.len() == 2;
+    }
+    !expect_constructor && *frames.last().unwrap()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(text: &str, line: u32, character: u32) -> Option<Identifier> {
+        identifier_at(text, Position { line, character })
+    }
+
+    #[test]
+    fn identifier_under_cursor() {
+        let identifier = at("main = greet name", 0, 9).unwrap();
+        assert_eq!(identifier.qualifier, None);
+        assert_eq!(identifier.name, "greet");
+        assert_eq!(identifier.range, line_range(0, 7, 12));
+    }
+
+    #[test]
+    fn identifier_just_after_cursor() {
+        let identifier = at("main = greet", 0, 12).unwrap();
+        assert_eq!(identifier.name, "greet");
+        assert_eq!(at("main = greet ", 0, 13), None);
+    }
+
+    #[test]
+    fn qualified_identifier() {
+        let text = "x = Data.Map.insert k v m";
+        for character in [5, 10, 15] {
+            let identifier = at(text, 0, character).unwrap();
+            assert_eq!(identifier.qualifier.as_deref(), Some("Data.Map"));
+            assert_eq!(identifier.name, "insert");
+            assert_eq!(identifier.range, line_range(0, 13, 19));
+        }
+    }
+
+    #[test]
+    fn record_access_is_not_qualified() {
+        let text = "x = person.name";
+        let identifier = at(text, 0, 12).unwrap();
+        assert_eq!(identifier.qualifier, None);
+        assert_eq!(identifier.name, "name");
+        assert_eq!(identifier.range, line_range(0, 11, 15));
+
+        assert_eq!(at(text, 0, 5).unwrap().name, "person");
+    }
+
+    #[test]
+    fn operator() {
+        let identifier = at("x = a <> b", 0, 7).unwrap();
+        assert_eq!(identifier.qualifier, None);
+        assert_eq!(identifier.name, "<>");
+        assert_eq!(identifier.range, line_range(0, 6, 8));
+    }
+
+    #[test]
+    fn identifier_with_prime() {
+        assert_eq!(at("x = go' 1", 0, 5).unwrap().name, "go'");
+    }
+
+    #[test]
+    fn nothing_under_cursor() {
+        assert_eq!(at("x =  y", 0, 4), None);
+        assert_eq!(at("x = y", 3, 0), None);
+    }
+
+    fn prefix_at(text: &str, character: u32) -> Option<CompletionPrefix> {
+        completion_prefix_at(text, Position { line: 0, character })
+    }
+
+    #[test]
+    fn completion_prefix() {
+        let prefix = prefix_at("x = inse", 8).unwrap();
+        assert_eq!(prefix.qualifier, None);
+        assert_eq!(prefix.prefix, "inse");
+        assert_eq!(prefix.range, line_range(0, 4, 8));
+    }
+
+    #[test]
+    fn qualified_completion_prefix() {
+        let prefix = prefix_at("x = Data.Map.ins", 16).unwrap();
+        assert_eq!(prefix.qualifier.as_deref(), Some("Data.Map"));
+        assert_eq!(prefix.prefix, "ins");
+        assert_eq!(prefix.range, line_range(0, 13, 16));
+
+        // Right after the dot everything in the module is a candidate
+        let prefix = prefix_at("x = Map.", 8).unwrap();
+        assert_eq!(prefix.qualifier.as_deref(), Some("Map"));
+        assert_eq!(prefix.prefix, "");
+    }
+
+    #[test]
+    fn record_access_prefix_is_not_qualified() {
+        let prefix = prefix_at("x = person.na", 13).unwrap();
+        assert_eq!(prefix.qualifier, None);
+        assert_eq!(prefix.prefix, "na");
+    }
+
+    #[test]
+    fn no_completion_prefix() {
+        assert_eq!(prefix_at("x = ", 4), None);
+    }
+
+    #[test]
+    fn import_module_under_cursor() {
+        let text = "module Main where\n\nimport Data.Map as Map\nimport Prelude";
+        let module_at = |line, character| import_module_at(text, Position { line, character });
+
+        assert_eq!(module_at(2, 7).as_deref(), Some("Data.Map"));
+        assert_eq!(module_at(2, 15).as_deref(), Some("Data.Map"));
+        assert_eq!(module_at(3, 10).as_deref(), Some("Prelude"));
+        // Not on the module name
+        assert_eq!(module_at(2, 20), None);
+        assert_eq!(module_at(0, 8), None);
+    }
+
+    fn in_type(text: &str, line: u32, character: u32) -> bool {
+        is_type_position(text, Position { line, character })
+    }
+
+    #[test]
+    fn newtype_declaration() {
+        let text = "newtype Name = Name String";
+        assert!(in_type(text, 0, 9));
+        assert!(!in_type(text, 0, 16));
+        assert!(in_type(text, 0, 22));
+    }
+
+    #[test]
+    fn data_declaration_constructors() {
+        let text = "data Shape\n  = Circle Number\n  | Rect { width :: Number }\n  | Named Name";
+        assert!(in_type(text, 0, 6));
+        assert!(!in_type(text, 1, 5));
+        assert!(in_type(text, 1, 13));
+        assert!(!in_type(text, 2, 5));
+        assert!(in_type(text, 2, 22));
+        assert!(!in_type(text, 3, 5));
+        assert!(in_type(text, 3, 11));
+    }
+
+    #[test]
+    fn signatures_and_annotations() {
+        let text = "greet :: Name -> String\ngreet (Name n) = n\n\nx = (Name \"a\" :: Name)";
+        assert!(in_type(text, 0, 10));
+        assert!(!in_type(text, 1, 8));
+        assert!(!in_type(text, 3, 6));
+        assert!(in_type(text, 3, 19));
+    }
+
+    #[test]
+    fn instance_head_and_body() {
+        let text = "instance showName :: Show Name where\n  show (Name n) = n";
+        assert!(in_type(text, 0, 27));
+        assert!(!in_type(text, 1, 9));
+    }
+
+    #[test]
+    fn import_list() {
+        let text = "import Data.Name (Name(Name), class Named)";
+        assert!(in_type(text, 0, 18));
+        assert!(!in_type(text, 0, 24));
+        assert!(in_type(text, 0, 37));
+    }
+
+    fn application(text: &str, character: u32) -> Option<(String, u32)> {
+        application_at(text, Position { line: 0, character })
+            .map(|application| (application.function.name, application.argument))
+    }
+
+    #[test]
+    fn application_argument_counting() {
+        let text = "x = foldl add 0 xs";
+        assert_eq!(application(text, 10), Some(("foldl".to_string(), 0)));
+        assert_eq!(application(text, 13), Some(("foldl".to_string(), 0)));
+        assert_eq!(application(text, 14), Some(("foldl".to_string(), 1)));
+        assert_eq!(application(text, 16), Some(("foldl".to_string(), 2)));
+        assert_eq!(application(text, 18), Some(("foldl".to_string(), 2)));
+        // On the function name itself
+        assert_eq!(application(text, 7), None);
+    }
+
+    #[test]
+    fn nested_application() {
+        let text = "x = foo (bar a ) b";
+        assert_eq!(application(text, 13), Some(("bar".to_string(), 0)));
+        assert_eq!(application(text, 15), Some(("bar".to_string(), 1)));
+        assert_eq!(application(text, 17), Some(("foo".to_string(), 1)));
+        // Directly inside the brackets, the group is the argument of the outer function
+        assert_eq!(application(text, 9), Some(("foo".to_string(), 0)));
+    }
+
+    #[test]
+    fn operators_and_keywords_end_applications() {
+        assert_eq!(
+            application("x = foo a <> bar ", 17),
+            Some(("bar".to_string(), 0))
+        );
+        assert_eq!(application("x = if foo then ", 16), None);
+        assert_eq!(
+            application("x = foo \"a b\" ", 14),
+            Some(("foo".to_string(), 1))
+        );
+    }
+
+    #[test]
+    fn qualified_application() {
+        let application = application_at(
+            "x = Map.insert k ",
+            Position {
+                line: 0,
+                character: 17,
+            },
+        )
+        .unwrap();
+        assert_eq!(application.function.qualifier.as_deref(), Some("Map"));
+        assert_eq!(application.function.name, "insert");
+        assert_eq!(application.function.range, line_range(0, 8, 14));
+        assert_eq!(application.argument, 1);
+    }
+
+    #[test]
+    fn function_type_arguments() {
+        assert_eq!(
+            split_function_type("forall a. Show a => a -> Maybe a -> String"),
+            vec!["a", "Maybe a", "String"]
+        );
+        assert_eq!(
+            split_function_type("(a -> b) -> Array a -> Array b"),
+            vec!["(a -> b)", "Array a", "Array b"]
+        );
+        assert_eq!(
+            split_function_type("forall r. { f :: Int -> Int | r } -> Int"),
+            vec!["{ f :: Int -> Int | r }", "Int"]
+        );
+        assert_eq!(split_function_type("Effect Unit"), vec!["Effect Unit"]);
+    }
+
+    #[test]
+    fn function_type_with_constrained_parameter() {
+        let signature = "forall a. Show a => (forall b. Eq b => b -> a) -> String";
+        let ranges = function_type_ranges(signature);
+        assert_eq!(ranges, vec![20..46, 50..56]);
+        assert_eq!(&signature[ranges[0].clone()], "(forall b. Eq b => b -> a)");
+        assert_eq!(
+            split_function_type(" forall a. a -> a -- identity"),
+            vec!["a", "a"]
+        );
+    }
+}
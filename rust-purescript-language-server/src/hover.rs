@@ -0,0 +1,71 @@
+use crate::document;
+use crate::ide_server::{CompletionInfo, IdeClient, commands as ide_commands};
+use anyhow::Result;
+use lsp_types::{Hover, HoverContents, MarkupContent, MarkupKind, Position};
+
+/// Get hover information for the identifier at a position
+pub async fn hover(ide: &IdeClient, content: &str, position: Position) -> Result<Option<Hover>> {
+    let Some(identifier) = document::identifier_at(content, position) else {
+        return Ok(None);
+    };
+
+    let results = ide_commands::type_search_in_module(
+        ide,
+        content,
+        identifier.qualifier.as_deref(),
+        &identifier.name,
+    )
+    .await?;
+
+    if results.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(Hover {
+        contents: HoverContents::Markup(MarkupContent {
+            kind: MarkupKind::Markdown,
+            value: results
+                .iter()
+                .map(render_completion_info)
+                .collect::<Vec<_>>()
+                .join("\n\n---\n\n"),
+        }),
+        range: Some(identifier.range),
+    }))
+}
+
+/// Render a declaration's signature and documentation as Markdown
+pub fn render_completion_info(info: &CompletionInfo) -> String {
+    let mut markdown = format!(
+        "```purescript\n{} :: {}\n```",
+        info.identifier, info.type_info
+    );
+
+    // Type synonyms in the signature are expanded by the IDE server
+    if let Some(expanded_type) = info
+        .expanded_type
+        .as_deref()
+        .filter(|expanded| !same_type(expanded, &info.type_info))
+    {
+        markdown.push_str(&format!("\n\nExpands to `{}`", expanded_type.trim()));
+    }
+
+    markdown.push_str(&format!("\n\n*{}*", info.module));
+
+    if let Some(documentation) = info
+        .documentation
+        .as_deref()
+        .map(str::trim)
+        .filter(|doc| !doc.is_empty())
+    {
+        markdown.push_str("\n\n");
+        markdown.push_str(documentation);
+    }
+
+    markdown
+}
+
+/// Compare two rendered types, ignoring how they are split over lines
+fn same_type(a: &str, b: &str) -> bool {
+    a.split_whitespace().eq(b.split_whitespace())
+}
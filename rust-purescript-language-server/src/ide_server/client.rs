@@ -0,0 +1,280 @@
+use crate::ide_server::{IdeCommand, IdeResponse};
+use serde::de::DeserializeOwned;
+use std::sync::Arc;
+use std::time::Duration;
+use thiserror::Error;
+use tokio::io::{AsyncBufReadExt, AsyncWriteExt, BufReader};
+use tokio::net::TcpStream;
+use tokio::sync::{Mutex, Semaphore};
+
+/// Maximum number of commands in flight at once
+const MAX_CONCURRENT_COMMANDS: usize = 8;
+
+const CONNECT_TIMEOUT: Duration = Duration::from_secs(2);
+
+/// Timeout of commands that only query the loaded modules
+const QUERY_TIMEOUT: Duration = Duration::from_secs(10);
+
+/// Timeout of commands that typecheck or load modules, which takes a while in big projects
+const COMPILE_TIMEOUT: Duration = Duration::from_secs(120);
+
+/// Errors talking to the IDE server
+#[derive(Debug, Error)]
+pub enum IdeClientError {
+    #[error("IDE server refused the connection on port {port}")]
+    ConnectionRefused { port: u16 },
+    #[error("IDE server {command} command timed out after {}s", timeout.as_secs())]
+    Timeout { command: String, timeout: Duration },
+    #[error("IDE server connection failed: {0}")]
+    Io(#[from] std::io::Error),
+    #[error("Could not encode IDE server {command} command: {reason}")]
+    MalformedRequest { command: String, reason: String },
+    #[error("Malformed response to IDE server {command} command: {reason}")]
+    MalformedResponse { command: String, reason: String },
+    #[error("IDE {command} command failed: {message}")]
+    CommandFailed { command: String, message: String },
+}
+
+impl IdeClientError {
+    /// Whether the IDE server could not be reached or did not answer in time, rather
+    /// than answering with an error
+    pub fn is_unavailable(&self) -> bool {
+        matches!(self, Self::ConnectionRefused { .. } | Self::Timeout { .. })
+    }
+}
+
+/// Connection to a running `purs ide server`
+///
+/// The IDE server answers a single command per connection and then closes it, so
+/// every command gets its own connection and connections cannot be kept open.
+/// Queries run concurrently, as the server handles each connection on its own
+/// thread, up to `MAX_CONCURRENT_COMMANDS`. Commands that change the server's state
+/// (`rebuild`, `load`, `reset`, `quit`) are serialized so their results do not interleave.
+///
+/// Cheap to clone; clones share the concurrency limits.
+#[derive(Debug, Clone)]
+pub struct IdeClient {
+    port: u16,
+    permits: Arc<Semaphore>,
+    state_changes: Arc<Mutex<()>>,
+}
+
+impl IdeClient {
+    pub fn new(port: u16) -> Self {
+        Self {
+            port,
+            permits: Arc::new(Semaphore::new(MAX_CONCURRENT_COMMANDS)),
+            state_changes: Arc::new(Mutex::new(())),
+        }
+    }
+
+    pub fn port(&self) -> u16 {
+        self.port
+    }
+
+    /// Send a command and return the raw response
+    ///
+    /// Compilation errors are not an error here: the IDE server reports them with
+    /// `resultType: "error"` and the errors in the `result` field.
+    pub async fn send(&self, command: IdeCommand) -> Result<IdeResponse, IdeClientError> {
+        let _state_change = if changes_state(command.name()) {
+            Some(self.state_changes.lock().await)
+        } else {
+            None
+        };
+        self.send_unserialized(&command).await
+    }
+
+    /// Send a command that changes the server's state, then `restore` to undo it
+    ///
+    /// No other state change can run in between, and `restore` is sent even if the
+    /// command fails. Returns the response to the first command.
+    pub async fn send_and_restore(
+        &self,
+        command: IdeCommand,
+        restore: IdeCommand,
+    ) -> Result<IdeResponse, IdeClientError> {
+        let _state_change = self.state_changes.lock().await;
+        let response = self.send_unserialized(&command).await;
+        self.send_unserialized(&restore).await?;
+        response
+    }
+
+    /// Send a command without waiting for state changes in flight
+    async fn send_unserialized(&self, command: &IdeCommand) -> Result<IdeResponse, IdeClientError> {
+        let timeout = command_timeout(command.name());
+        let _permit = self
+            .permits
+            .acquire()
+            .await
+            .expect("IDE client semaphore is never closed");
+
+        tokio::time::timeout(timeout, self.exchange(command))
+            .await
+            .map_err(|_| IdeClientError::Timeout {
+                command: command.name().to_string(),
+                timeout,
+            })?
+    }
+
+    /// Send a command and return its result, failing if the IDE server reports an error
+    pub async fn send_expecting_success(
+        &self,
+        command: IdeCommand,
+    ) -> Result<serde_json::Value, IdeClientError> {
+        let name = command.name().to_string();
+        let response = self.send(command).await?;
+
+        if response.result_type.as_deref() == Some("error") {
+            let message = match response.result {
+                Some(serde_json::Value::String(message)) => message,
+                Some(other) => other.to_string(),
+                None => "no details".to_string(),
+            };
+            return Err(IdeClientError::CommandFailed {
+                command: name,
+                message,
+            });
+        }
+
+        Ok(response.result.unwrap_or(serde_json::Value::Null))
+    }
+
+    /// Send a command and deserialize its successful result
+    pub async fn request<T: DeserializeOwned>(
+        &self,
+        command: IdeCommand,
+    ) -> Result<T, IdeClientError> {
+        let name = command.name().to_string();
+        let result = self.send_expecting_success(command).await?;
+
+        serde_json::from_value(result).map_err(|e| IdeClientError::MalformedResponse {
+            command: name,
+            reason: e.to_string(),
+        })
+    }
+
+    /// Write the command on a new connection and read the one-line response
+    async fn exchange(&self, command: &IdeCommand) -> Result<IdeResponse, IdeClientError> {
+        let mut stream = self.connect().await?;
+
+        // IDE server request (not JSON-RPC), terminated by a newline
+        let request =
+            serde_json::to_string(command).map_err(|e| IdeClientError::MalformedRequest {
+                command: command.name().to_string(),
+                reason: e.to_string(),
+            })?;
+        stream
+            .write_all(format!("{}\n", request).as_bytes())
+            .await?;
+
+        let mut line = String::new();
+        BufReader::new(stream).read_line(&mut line).await?;
+
+        let malformed = |reason: String| IdeClientError::MalformedResponse {
+            command: command.name().to_string(),
+            reason,
+        };
+        if line.trim().is_empty() {
+            return Err(malformed(
+                "connection closed without a response".to_string(),
+            ));
+        }
+
+        let response: serde_json::Value =
+            serde_json::from_str(&line).map_err(|e| malformed(e.to_string()))?;
+        let result_type = match response.get("resultType") {
+            Some(serde_json::Value::String(result_type))
+                if result_type == "success" || result_type == "error" =>
+            {
+                result_type.clone()
+            }
+            _ => return Err(malformed("missing or unknown resultType".to_string())),
+        };
+
+        Ok(IdeResponse {
+            result_type: Some(result_type),
+            result: response.get("result").cloned(),
+        })
+    }
+
+    async fn connect(&self) -> Result<TcpStream, IdeClientError> {
+        let address = format!("127.0.0.1:{}", self.port);
+        match tokio::time::timeout(CONNECT_TIMEOUT, TcpStream::connect(address)).await {
+            Ok(Ok(stream)) => Ok(stream),
+            Ok(Err(e)) if e.kind() == std::io::ErrorKind::ConnectionRefused => {
+                Err(IdeClientError::ConnectionRefused { port: self.port })
+            }
+            Ok(Err(e)) => Err(e.into()),
+            Err(_) => Err(IdeClientError::Timeout {
+                command: "connect".to_string(),
+                timeout: CONNECT_TIMEOUT,
+            }),
+        }
+    }
+}
+
+/// Commands that change the loaded modules
+fn changes_state(command: &str) -> bool {
+    matches!(command, "rebuild" | "load" | "reset" | "quit")
+}
+
+fn command_timeout(command: &str) -> Duration {
+    if matches!(command, "rebuild" | "load") {
+        COMPILE_TIMEOUT
+    } else {
+        QUERY_TIMEOUT
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ide_server::process;
+
+    #[tokio::test]
+    async fn test_closed_port_is_unavailable() {
+        let port = process::find_available_port().unwrap();
+        let error = IdeClient::new(port)
+            .send(IdeCommand::Cwd)
+            .await
+            .unwrap_err();
+        assert!(matches!(error, IdeClientError::ConnectionRefused { .. }));
+        assert!(error.is_unavailable());
+    }
+
+    #[tokio::test]
+    async fn test_error_result_fails_the_command() {
+        let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+        let port = listener.local_addr().unwrap().port();
+        tokio::spawn(async move {
+            let (mut stream, _) = listener.accept().await.unwrap();
+            let mut request = String::new();
+            let (read, mut write) = stream.split();
+            BufReader::new(read).read_line(&mut request).await.unwrap();
+            write
+                .write_all(b"{\"resultType\":\"error\",\"result\":\"Module not found\"}\n")
+                .await
+                .unwrap();
+        });
+
+        let error = IdeClient::new(port)
+            .send_expecting_success(IdeCommand::Reset)
+            .await
+            .unwrap_err();
+        assert_eq!(
+            error.to_string(),
+            "IDE reset command failed: Module not found"
+        );
+        assert!(!error.is_unavailable());
+    }
+
+    #[test]
+    fn test_failed_command_is_not_unavailable() {
+        let error = IdeClientError::CommandFailed {
+            command: "type".to_string(),
+            message: "Unknown module".to_string(),
+        };
+        assert!(!error.is_unavailable());
+    }
+}
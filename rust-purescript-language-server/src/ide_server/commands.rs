@@ -1,85 +1,355 @@
-use crate::ide_server::{IdeCommand, IdeResponse, RebuildResult};
+use crate::document;
+use crate::ide_server::{
+    CodegenTarget, CompleteOptions, CompletionInfo, ErrorSpan, Filter, IdeClient, IdeCommand,
+    IdeResponse, ImportCommand, ImportResult, ListType, Matcher, ModuleImports, Namespace,
+    RebuildError, RebuildResult,
+};
 use anyhow::Result;
-use serde_json::json;
-use tokio::io::{AsyncReadExt, AsyncWriteExt};
-use tokio::net::TcpStream;
-
-/// Send a command to the IDE server via TCP
-pub async fn send_command(port: u16, command: IdeCommand) -> Result<IdeResponse> {
-    let mut stream = TcpStream::connect(format!("127.0.0.1:{}", port)).await?;
-
-    // Create IDE server request (not JSON-RPC)
-    let request = serde_json::json!({
-        "command": command.command,
-        "params": command.params
+
+/// Look up the type and documentation of an identifier
+pub async fn type_search(
+    ide: &IdeClient,
+    search: &str,
+    filters: &[Filter],
+    current_module: Option<&str>,
+) -> Result<Vec<CompletionInfo>> {
+    let command = IdeCommand::Type {
+        search: search.to_string(),
+        filters: filters.to_vec(),
+        current_module: current_module.map(str::to_string),
+    };
+
+    Ok(ide.request(command).await?)
+}
+
+/// Load the externs of every compiled module into the IDE server
+pub async fn load_all(ide: &IdeClient) -> Result<()> {
+    load(ide, &[]).await
+}
+
+/// Load the externs of some modules into the IDE server
+pub async fn load(ide: &IdeClient, modules: &[String]) -> Result<()> {
+    let command = IdeCommand::Load {
+        modules: modules.to_vec(),
+    };
+
+    ide.send_expecting_success(command).await?;
+    Ok(())
+}
+
+/// List the modules whose externs are loaded
+pub async fn list_loaded_modules(ide: &IdeClient) -> Result<Vec<String>> {
+    Ok(ide
+        .request(IdeCommand::List(ListType::LoadedModules))
+        .await?)
+}
+
+/// List the modules compiled to the output directory
+pub async fn list_available_modules(ide: &IdeClient) -> Result<Vec<String>> {
+    Ok(ide
+        .request(IdeCommand::List(ListType::AvailableModules))
+        .await?)
+}
+
+/// List the imports of a file, as the IDE server parses them from disk
+#[allow(dead_code)]
+pub async fn list_imports(ide: &IdeClient, file_path: &str) -> Result<ModuleImports> {
+    let command = IdeCommand::List(ListType::Import {
+        file: file_path.to_string(),
     });
 
-    let request_json = serde_json::to_string(&request)?;
-    let request_bytes = format!("{}\n", request_json);
+    Ok(ide.request(command).await?)
+}
 
-    // Send request
-    stream.write_all(request_bytes.as_bytes()).await?;
+/// Get the working directory of the IDE server
+pub async fn cwd(ide: &IdeClient) -> Result<String> {
+    Ok(ide.request(IdeCommand::Cwd).await?)
+}
 
-    // Read response
-    let mut buffer = Vec::new();
-    stream.read_to_end(&mut buffer).await?;
-    let response_str = String::from_utf8(buffer)?;
+/// Unload every module from the IDE server
+pub async fn reset(ide: &IdeClient) -> Result<()> {
+    ide.send_expecting_success(IdeCommand::Reset).await?;
+    Ok(())
+}
 
-    // Parse IDE server response
-    let response: serde_json::Value = serde_json::from_str(&response_str)?;
+/// Ask the IDE server to stop
+pub async fn quit(ide: &IdeClient) -> Result<()> {
+    ide.send_expecting_success(IdeCommand::Quit).await?;
+    Ok(())
+}
 
-    // Always return the result, whether it contains errors or not
-    // The IDE server uses resultType: "error" to indicate compilation errors,
-    // but the errors are still in the result field
-    Ok(IdeResponse {
-        result: response.get("result").cloned(),
-        error: None,
-    })
+/// Typecheck a module and return its errors and warnings
+///
+/// `file` is a path or `data:` followed by the module's source; `actual_file` then
+/// names the file the source belongs to. Without `codegen` the server's default
+/// targets are generated.
+pub async fn rebuild(
+    ide: &IdeClient,
+    file: String,
+    actual_file: Option<String>,
+    codegen: Option<Vec<CodegenTarget>>,
+) -> Result<Vec<RebuildError>> {
+    let command = IdeCommand::Rebuild {
+        file,
+        actual_file,
+        codegen,
+    };
+
+    rebuild_errors(ide.send(command).await?)
+}
+
+/// Typecheck in-memory content of a module without keeping it loaded
+///
+/// `content` is typechecked, then `original` (or the file on disk when it is `None`)
+/// is rebuilt so the IDE server's state matches it again. Neither rebuild generates
+/// any output.
+pub async fn rebuild_scratch(
+    ide: &IdeClient,
+    file_path: &str,
+    content: &str,
+    original: Option<&str>,
+) -> Result<Vec<RebuildError>> {
+    let rebuild = |content: Option<&str>| match content {
+        Some(content) => IdeCommand::Rebuild {
+            file: format!("data:{}", content),
+            actual_file: Some(file_path.to_string()),
+            codegen: Some(vec![]),
+        },
+        None => IdeCommand::Rebuild {
+            file: file_path.to_string(),
+            actual_file: None,
+            codegen: Some(vec![]),
+        },
+    };
+
+    rebuild_errors(
+        ide.send_and_restore(rebuild(Some(content)), rebuild(original))
+            .await?,
+    )
+}
+
+/// Get the errors of a rebuild response
+///
+/// Both the warnings of a successful rebuild and the errors of a failed one are
+/// returned as the result.
+fn rebuild_errors(response: IdeResponse) -> Result<Vec<RebuildError>> {
+    match response.result {
+        Some(result @ serde_json::Value::Array(_)) => Ok(serde_json::from_value(result)?),
+        Some(serde_json::Value::String(message)) => {
+            Err(anyhow::anyhow!("IDE rebuild command failed: {}", message))
+        }
+        _ => Ok(vec![]),
+    }
 }
 
 /// Rebuild a single file with optional content (using data: prefix)
 /// When content is provided, the IDE server will use the in-memory content
 /// instead of reading from disk. This is useful for rebuild-on-change.
 pub async fn rebuild_file_with_content(
-    port: u16,
+    ide: &IdeClient,
     file_path: &str,
     content: Option<&str>,
 ) -> Result<RebuildResult> {
     // If content is provided, use the data: prefix format
-    let file_param = if let Some(content) = content {
-        format!("data:{}", content)
-    } else {
-        file_path.to_string()
+    let (file, actual_file) = match content {
+        Some(content) => (format!("data:{}", content), Some(file_path.to_string())),
+        None => (file_path.to_string(), None),
     };
 
-    let command = IdeCommand {
-        command: "rebuild".to_string(),
-        params: Some(json!({
-            "file": file_param
-        })),
-    };
+    let errors = rebuild(ide, file, actual_file, None).await?;
 
-    let response = send_command(port, command).await?;
-
-    if let Some(result) = response.result {
-        // The IDE server returns errors directly in the result array
-        if let Some(errors) = result.as_array() {
-            if !errors.is_empty() {
-                let rebuild_errors: Vec<crate::ide_server::RebuildError> =
-                    serde_json::from_value(result)?;
-                return Ok(RebuildResult {
-                    result: "rebuild completed".to_string(),
-                    errors: Some(rebuild_errors),
-                    warnings: None,
-                });
-            }
-        }
-    }
-
-    // If no errors, return success
     Ok(RebuildResult {
         result: "rebuild completed".to_string(),
-        errors: None,
+        errors: (!errors.is_empty()).then_some(errors),
         warnings: None,
     })
 }
+
+/// Look up an identifier as seen from a module, respecting its imports
+///
+/// Qualified names are searched in the modules imported under that qualifier.
+/// Unqualified names are searched through the module's imports first, then
+/// among the module's own declarations.
+pub async fn type_search_in_module(
+    ide: &IdeClient,
+    module_text: &str,
+    qualifier: Option<&str>,
+    name: &str,
+) -> Result<Vec<CompletionInfo>> {
+    let current_module = document::module_name(module_text);
+    let exact = Filter::Exact(name.to_string());
+
+    if let Some(qualifier) = qualifier {
+        let imports = document::parse_imports(module_text);
+        let modules = document::modules_for_qualifier(&imports, qualifier);
+        // A full module name can also be used as a qualifier without an alias
+        let modules = if modules.is_empty() {
+            vec![qualifier.to_string()]
+        } else {
+            modules
+        };
+        return type_search(
+            ide,
+            name,
+            &[exact, Filter::Modules(modules)],
+            current_module.as_deref(),
+        )
+        .await;
+    }
+
+    let dependencies = Filter::Dependencies {
+        qualifier: None,
+        module_text: document::module_header(module_text),
+    };
+    let results = type_search(
+        ide,
+        name,
+        &[exact.clone(), dependencies],
+        current_module.as_deref(),
+    )
+    .await?;
+
+    if !results.is_empty() {
+        return Ok(results);
+    }
+
+    match current_module {
+        Some(current_module) => {
+            type_search(
+                ide,
+                name,
+                &[exact, Filter::Modules(vec![current_module.clone()])],
+                Some(&current_module),
+            )
+            .await
+        }
+        None => Ok(results),
+    }
+}
+
+/// Get completions for a prefix
+pub async fn complete(
+    ide: &IdeClient,
+    filters: &[Filter],
+    current_module: Option<&str>,
+    max_results: usize,
+) -> Result<Vec<CompletionInfo>> {
+    let command = IdeCommand::Complete {
+        filters: filters.to_vec(),
+        matcher: None,
+        current_module: current_module.map(str::to_string),
+        options: CompleteOptions {
+            max_results: Some(max_results),
+            group_reexports: true,
+        },
+    };
+
+    Ok(ide.request(command).await?)
+}
+
+/// Get completions fuzzily matching a search across all loaded modules
+///
+/// Uses the IDE server's `flex` matcher, so `mkp` matches `makePerson`.
+pub async fn complete_fuzzy(
+    ide: &IdeClient,
+    search: &str,
+    max_results: usize,
+) -> Result<Vec<CompletionInfo>> {
+    let command = IdeCommand::Complete {
+        filters: vec![],
+        matcher: Some(Matcher::Flex {
+            search: search.to_string(),
+        }),
+        current_module: None,
+        options: CompleteOptions {
+            max_results: Some(max_results),
+            group_reexports: true,
+        },
+    };
+
+    Ok(ide.request(command).await?)
+}
+
+/// Rewrite the imports of a file, returning the full rewritten file as lines
+///
+/// The IDE server reads the file from disk. If the identifier is ambiguous it
+/// answers with the candidate declarations instead, which is reported as an error
+/// so the caller can narrow the `filters`.
+pub async fn import(
+    ide: &IdeClient,
+    file_path: &str,
+    import_command: &ImportCommand,
+    filters: &[Filter],
+) -> Result<Vec<String>> {
+    let command = IdeCommand::Import {
+        file: file_path.to_string(),
+        outfile: None,
+        filters: filters.to_vec(),
+        import_command: import_command.clone(),
+    };
+
+    match ide.request(command).await? {
+        ImportResult::Lines(lines) => Ok(lines),
+        ImportResult::Ambiguous(candidates) => Err(anyhow::anyhow!(
+            "Ambiguous import, candidates: {}",
+            candidates
+                .iter()
+                .map(|candidate| candidate.module.as_str())
+                .collect::<Vec<_>>()
+                .join(", ")
+        )),
+        ImportResult::Written(message) => {
+            Err(anyhow::anyhow!("Unexpected IDE import result: {}", message))
+        }
+    }
+}
+
+/// Find all usages of a declaration across the loaded modules
+pub async fn usages(
+    ide: &IdeClient,
+    module: &str,
+    namespace: Namespace,
+    identifier: &str,
+) -> Result<Vec<ErrorSpan>> {
+    let command = IdeCommand::Usages {
+        module: module.to_string(),
+        namespace,
+        identifier: identifier.to_string(),
+    };
+
+    Ok(ide.request(command).await?)
+}
+
+/// Split a pattern variable into one clause per constructor of its type
+///
+/// `begin` and `end` are the 0-based char columns of the variable within `line`.
+/// Returns the replacement lines for the clause.
+pub async fn case_split(
+    ide: &IdeClient,
+    line: &str,
+    begin: u32,
+    end: u32,
+    type_info: &str,
+) -> Result<Vec<String>> {
+    let command = IdeCommand::CaseSplit {
+        line: line.to_string(),
+        begin,
+        end,
+        annotations: false,
+        type_info: type_info.to_string(),
+    };
+
+    Ok(ide.request(command).await?)
+}
+
+/// Generate a function clause for a type signature line such as `foo :: Int -> String`
+///
+/// Returns the lines to put in place of the signature line.
+pub async fn add_clause(ide: &IdeClient, line: &str) -> Result<Vec<String>> {
+    let command = IdeCommand::AddClause {
+        line: line.to_string(),
+        annotations: false,
+    };
+
+    Ok(ide.request(command).await?)
+}
@@ -1,27 +1,307 @@
+pub mod client;
 pub mod commands;
 pub mod process;
 pub mod protocol;
+pub mod supervisor;
+
+#[cfg(test)]
+mod tests;
 
 use serde::{Deserialize, Serialize};
 
-/// IDE server command types
-#[derive(Debug, Serialize)]
-pub struct IdeCommand {
-    pub command: String,
-    pub params: Option<serde_json::Value>,
+pub use client::IdeClient;
+
+/// A command of the `purs ide` protocol
+///
+/// Serialized as `{"command": ..., "params": ...}`, one command per connection.
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "command",
+    content = "params",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum IdeCommand {
+    /// Load the externs of the given modules, or of every compiled module if empty
+    Load {
+        modules: Vec<String>,
+    },
+    /// Look up declarations by exact name
+    Type {
+        search: String,
+        filters: Vec<Filter>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        current_module: Option<String>,
+    },
+    /// Look up declarations matching the filters and an optional matcher
+    Complete {
+        filters: Vec<Filter>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        matcher: Option<Matcher>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        current_module: Option<String>,
+        options: CompleteOptions,
+    },
+    /// Split the pattern variable between `begin` and `end` on a clause line
+    CaseSplit {
+        line: String,
+        begin: u32,
+        end: u32,
+        annotations: bool,
+        #[serde(rename = "type")]
+        type_info: String,
+    },
+    /// Generate a clause for a type signature line
+    AddClause {
+        line: String,
+        annotations: bool,
+    },
+    /// Rewrite the imports of a file, writing them to `outfile` if given
+    Import {
+        file: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        outfile: Option<String>,
+        filters: Vec<Filter>,
+        import_command: ImportCommand,
+    },
+    List(ListType),
+    /// Find the usages of a declaration across the loaded modules
+    Usages {
+        module: String,
+        namespace: Namespace,
+        identifier: String,
+    },
+    /// Typecheck a module and update the loaded modules with it
+    ///
+    /// `file` may be `data:` followed by the module's source, in which case
+    /// `actual_file` tells the server which file the source belongs to.
+    Rebuild {
+        file: String,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        actual_file: Option<String>,
+        #[serde(skip_serializing_if = "Option::is_none")]
+        codegen: Option<Vec<CodegenTarget>>,
+    },
+    /// The working directory of the server
+    Cwd,
+    /// Unload every module
+    Reset,
+    /// Stop the server
+    Quit,
+}
+
+impl IdeCommand {
+    /// The command's name in the protocol
+    pub fn name(&self) -> &'static str {
+        match self {
+            IdeCommand::Load { .. } => "load",
+            IdeCommand::Type { .. } => "type",
+            IdeCommand::Complete { .. } => "complete",
+            IdeCommand::CaseSplit { .. } => "caseSplit",
+            IdeCommand::AddClause { .. } => "addClause",
+            IdeCommand::Import { .. } => "import",
+            IdeCommand::List(_) => "list",
+            IdeCommand::Usages { .. } => "usages",
+            IdeCommand::Rebuild { .. } => "rebuild",
+            IdeCommand::Cwd => "cwd",
+            IdeCommand::Reset => "reset",
+            IdeCommand::Quit => "quit",
+        }
+    }
+}
+
+/// What the `list` command lists
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "type", rename_all = "camelCase")]
+pub enum ListType {
+    /// Modules whose externs are loaded
+    LoadedModules,
+    /// Modules compiled to the output directory
+    AvailableModules,
+    /// The imports of a file
+    Import { file: String },
+}
+
+/// Matchers ranking the results of `complete`
+#[derive(Debug, Clone, Serialize)]
+#[serde(
+    tag = "matcher",
+    content = "params",
+    rename_all = "camelCase",
+    rename_all_fields = "camelCase"
+)]
+pub enum Matcher {
+    /// Characters of the search appear in order, so `mkp` matches `makePerson`
+    Flex { search: String },
+}
+
+#[derive(Debug, Clone, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct CompleteOptions {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub max_results: Option<usize>,
+    /// Return a re-exported declaration once, listing the re-exporting modules
+    pub group_reexports: bool,
+}
+
+/// Namespaces declarations live in, as used by the `usages` command
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Namespace {
+    Value,
+    Type,
+    Kind,
+}
+
+/// Code generation targets of a `rebuild`
+#[allow(dead_code)]
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum CodegenTarget {
+    Js,
+    Sourcemaps,
+    Corefn,
+    Docs,
 }
 
 /// IDE server response types
 #[derive(Debug, Deserialize)]
 pub struct IdeResponse {
+    #[serde(rename = "resultType")]
+    pub result_type: Option<String>,
     pub result: Option<serde_json::Value>,
-    pub error: Option<IdeError>,
 }
 
+/// Filters narrowing the declarations searched by `type` and `complete`
+#[derive(Debug, Clone)]
+pub enum Filter {
+    /// Identifier must match exactly
+    Exact(String),
+    /// Identifier must start with the given prefix
+    Prefix(String),
+    /// Declaration must come from one of these modules
+    Modules(Vec<String>),
+    /// Declaration must be visible through the imports in `module_text`,
+    /// optionally only those imported under `qualifier`
+    Dependencies {
+        qualifier: Option<String>,
+        module_text: String,
+    },
+}
+
+impl Serialize for Filter {
+    fn serialize<S: serde::Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        let filter = match self {
+            Filter::Exact(search) => serde_json::json!({
+                "filter": "exact",
+                "params": { "search": search }
+            }),
+            Filter::Prefix(search) => serde_json::json!({
+                "filter": "prefix",
+                "params": { "search": search }
+            }),
+            Filter::Modules(modules) => serde_json::json!({
+                "filter": "modules",
+                "params": { "modules": modules }
+            }),
+            Filter::Dependencies {
+                qualifier,
+                module_text,
+            } => serde_json::json!({
+                "filter": "dependencies",
+                "params": { "qualifier": qualifier, "moduleText": module_text }
+            }),
+        };
+        filter.serialize(serializer)
+    }
+}
+
+/// Import rewrites performed by the `import` command
+#[derive(Debug, Clone, Serialize)]
+#[serde(tag = "importCommand")]
+pub enum ImportCommand {
+    /// `import Module as Qualifier`
+    #[serde(rename = "addQualifiedImport")]
+    Qualified { module: String, qualifier: String },
+    /// Add an identifier to an explicit import list, or `import Module as Qualifier`
+    /// when a qualifier is given
+    #[serde(rename = "addImport")]
+    Identifier {
+        identifier: String,
+        qualifier: Option<String>,
+    },
+}
+
+/// Declaration info returned by the `type` and `complete` commands
+#[derive(Debug, Deserialize, Clone)]
+pub struct CompletionInfo {
+    pub module: String,
+    pub identifier: String,
+    #[serde(rename = "type")]
+    pub type_info: String,
+    #[serde(rename = "expandedType")]
+    pub expanded_type: Option<String>,
+    #[serde(rename = "definedAt")]
+    pub defined_at: Option<ErrorSpan>,
+    pub documentation: Option<String>,
+    #[serde(rename = "exportedFrom", default)]
+    pub exported_from: Vec<String>,
+    #[serde(rename = "declarationType")]
+    pub declaration_type: Option<String>,
+}
+
+impl CompletionInfo {
+    /// The namespace the declaration lives in, as expected by the `usages` command
+    pub fn namespace(&self) -> Namespace {
+        match self.declaration_type.as_deref() {
+            Some("type") | Some("synonym") | Some("typeclass") | Some("typeoperator") => {
+                Namespace::Type
+            }
+            Some("kind") => Namespace::Kind,
+            _ => Namespace::Value,
+        }
+    }
+}
+
+/// Result of the `import` command
 #[derive(Debug, Deserialize)]
-pub struct IdeError {
-    pub code: i32,
-    pub message: String,
+#[serde(untagged)]
+pub enum ImportResult {
+    /// The rewritten file as lines, when no `outfile` was given
+    Lines(Vec<String>),
+    /// The identifier is exported by several modules and `filters` must narrow it down
+    Ambiguous(Vec<CompletionInfo>),
+    /// Confirmation that `outfile` was written
+    Written(String),
+}
+
+/// Result of `list` for the imports of a file
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ModuleImports {
+    pub module_name: Option<String>,
+    pub imports: Vec<ModuleImport>,
+}
+
+#[allow(dead_code)]
+#[derive(Debug, Deserialize)]
+pub struct ModuleImport {
+    pub module: String,
+    #[serde(flatten)]
+    pub import_type: ImportType,
+    pub qualifier: Option<String>,
+}
+
+#[derive(Debug, Deserialize, PartialEq)]
+#[serde(tag = "importType", rename_all = "camelCase")]
+pub enum ImportType {
+    /// `import Module`
+    Implicit,
+    /// `import Module (a, b)`
+    Explicit { identifiers: Vec<String> },
+    /// `import Module hiding (a, b)`
+    Hiding { identifiers: Vec<String> },
 }
 
 /// Rebuild result from purs ide server
@@ -47,13 +327,42 @@ pub struct RebuildError {
     pub module_name: Option<String>,
     pub position: ErrorPosition,
     pub suggestion: Option<ErrorSuggestion>,
+    /// Values that fit a typed hole, only attached to `HoleInferredType` errors
+    #[serde(rename = "pursIde")]
+    pub hole_completions: Option<HoleCompletions>,
+}
+
+impl RebuildError {
+    /// Get the type from the message of a `HoleInferredType` or `WildcardInferredType`
+    /// error, joined onto one line
+    pub fn inferred_type(&self) -> Option<String> {
+        let (_, rest) = self.message.split_once("inferred type")?;
+        let inferred: Vec<&str> = rest
+            .lines()
+            .skip(1)
+            .map(str::trim)
+            .skip_while(|line| line.is_empty())
+            .take_while(|line| !line.is_empty())
+            .collect();
+
+        (!inferred.is_empty()).then(|| inferred.join(" "))
+    }
+}
+
+/// The IDE server's suggestions for a typed hole such as `?name`
+#[derive(Debug, Deserialize, Clone)]
+pub struct HoleCompletions {
+    pub name: String,
+    /// Values of the hole's type; local values have an empty module and record
+    /// fields come as `_.field`
+    pub completions: Vec<CompletionInfo>,
 }
 
 fn default_string() -> String {
     "unknown".to_string()
 }
 
-#[derive(Debug, Deserialize, Clone)]
+#[derive(Debug, Deserialize, Clone, PartialEq)]
 pub struct ErrorPosition {
     #[serde(rename = "startLine")]
     pub start_line: u32,
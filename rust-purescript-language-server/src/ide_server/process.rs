@@ -1,3 +1,4 @@
+use crate::ide_server::{IdeClient, commands};
 use anyhow::Result;
 use std::io::{BufRead, BufReader};
 use std::net::{IpAddr, Ipv4Addr, SocketAddr};
@@ -167,3 +168,37 @@ pub async fn start_ide_server_async(
         sleep(Duration::from_millis(100)).await;
     }
 }
+
+/// Kill an IDE server process and reap it
+///
+/// Waiting for the process to exit blocks, so it happens on the blocking thread pool
+/// and callers must not hold the server state lock across it.
+pub async fn stop_ide_server(mut child: Child) -> Result<()> {
+    if child.try_wait()?.is_none() {
+        child.kill()?;
+    }
+    tokio::task::spawn_blocking(move || child.wait()).await??;
+    Ok(())
+}
+
+/// Connect to an IDE server started outside the language server
+///
+/// The server is probed with `cwd`, so a port without a `purs ide server` behind it
+/// fails here rather than on the first request, then asked to load the compiled
+/// modules if it has not loaded all of them yet. Returns the client and the server's working
+/// directory.
+pub async fn attach_ide_server(port: u16) -> Result<(IdeClient, String)> {
+    let ide = IdeClient::new(port);
+
+    let cwd = commands::cwd(&ide).await.map_err(|e| {
+        anyhow::anyhow!("No PureScript IDE server answering on port {}: {}", port, e)
+    })?;
+
+    let loaded = commands::list_loaded_modules(&ide).await?;
+    let available = commands::list_available_modules(&ide).await?;
+    if loaded.is_empty() || loaded.len() < available.len() {
+        commands::load_all(&ide).await?;
+    }
+
+    Ok((ide, cwd))
+}
@@ -0,0 +1,342 @@
+use crate::ide_server::{IdeClient, commands as ide_commands, process};
+use crate::types::ServerState;
+use serde::{Deserialize, Serialize};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+use tokio::sync::Mutex;
+use tower_lsp::Client;
+use tower_lsp::lsp_types::MessageType;
+use tower_lsp::lsp_types::notification::Notification;
+
+/// How often the IDE server process and port are checked
+const POLL_INTERVAL: Duration = Duration::from_secs(2);
+
+/// Consecutive failed connection attempts after which a running server is restarted
+const MAX_CONNECTION_FAILURES: u32 = 3;
+
+/// Delay before the first restart attempt, doubled after every failed attempt
+const INITIAL_BACKOFF: Duration = Duration::from_secs(1);
+
+const MAX_BACKOFF: Duration = Duration::from_secs(60);
+
+/// Restart attempts in a row, including crashes before `STABLE_UPTIME`, after which
+/// the supervisor gives up
+const MAX_RESTART_ATTEMPTS: u32 = 6;
+
+/// Uptime after which a restarted server counts as stable and earlier failures are forgotten
+const STABLE_UPTIME: Duration = Duration::from_secs(60);
+
+/// Restart delay and failure count of a supervisor
+///
+/// Kept across restarts, so a server that crashes again right after coming back
+/// keeps backing off and is eventually given up on.
+#[derive(Debug)]
+struct Backoff {
+    delay: Duration,
+    failures: u32,
+    started_at: Instant,
+}
+
+impl Backoff {
+    fn new() -> Self {
+        Self {
+            delay: INITIAL_BACKOFF,
+            failures: 0,
+            started_at: Instant::now(),
+        }
+    }
+
+    /// Forget earlier failures if the server stayed up long enough since its last start
+    fn reset_if_stable(&mut self) {
+        if self.started_at.elapsed() >= STABLE_UPTIME {
+            self.delay = INITIAL_BACKOFF;
+            self.failures = 0;
+        }
+    }
+
+    /// Count a failure and return the delay before the next attempt, or `None` once
+    /// the attempts are exhausted
+    fn next_delay(&mut self) -> Option<Duration> {
+        if self.failures >= MAX_RESTART_ATTEMPTS {
+            return None;
+        }
+        self.failures += 1;
+        let delay = self.delay;
+        self.delay = (self.delay * 2).min(MAX_BACKOFF);
+        Some(delay)
+    }
+
+    fn started(&mut self) {
+        self.started_at = Instant::now();
+    }
+}
+
+/// State of the IDE server, as reported to the client
+#[derive(Debug, Clone, Copy, PartialEq, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub enum IdeServerStatus {
+    Running,
+    Restarting,
+    Failed,
+}
+
+/// Parameters of the `purescript/ideServerStatus` notification
+#[derive(Debug, Clone, Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct IdeServerStatusParams {
+    pub status: IdeServerStatus,
+    pub port: Option<u16>,
+    pub message: Option<String>,
+}
+
+/// Notification sent to the client whenever the IDE server changes state
+pub enum IdeServerStatusNotification {}
+
+impl Notification for IdeServerStatusNotification {
+    type Params = IdeServerStatusParams;
+    const METHOD: &'static str = "purescript/ideServerStatus";
+}
+
+/// Report a state transition with a status notification and, unless it is a plain
+/// successful start, a `window/showMessage`
+pub async fn report_status(
+    client: &Client,
+    status: IdeServerStatus,
+    port: Option<u16>,
+    message: Option<String>,
+) {
+    if let Some(message) = &message {
+        let message_type = match status {
+            IdeServerStatus::Running => MessageType::INFO,
+            IdeServerStatus::Restarting => MessageType::WARNING,
+            IdeServerStatus::Failed => MessageType::ERROR,
+        };
+        client.show_message(message_type, message).await;
+    }
+
+    client
+        .send_notification::<IdeServerStatusNotification>(IdeServerStatusParams {
+            status,
+            port,
+            message,
+        })
+        .await;
+}
+
+/// Watch the IDE server process of a generation and restart it when it dies
+///
+/// The server is considered dead when its process exits or when its port refuses
+/// connections several times in a row. Restarts back off exponentially, until a
+/// restarted server stays up for `STABLE_UPTIME`, and the modules are reloaded
+/// afterwards. The supervisor stops as soon as the process is
+/// replaced or stopped elsewhere (configuration change or shutdown).
+pub async fn supervise(client: Client, state: Arc<Mutex<ServerState>>, generation: u64) {
+    let mut connection_failures = 0;
+    let mut backoff = Backoff::new();
+
+    loop {
+        tokio::time::sleep(POLL_INTERVAL).await;
+
+        let (exit_status, port) = {
+            let mut state = state.lock().await;
+            if state.ide_server.generation != generation {
+                return;
+            }
+            let Some(child) = state.ide_server.process.as_mut() else {
+                return;
+            };
+            (
+                child.try_wait().ok().flatten(),
+                state.ide_server.client.as_ref().map(IdeClient::port),
+            )
+        };
+
+        let reason = match (exit_status, port) {
+            (Some(exit_status), _) => format!("exited with {}", exit_status),
+            (None, Some(port)) => {
+                if tokio::net::TcpStream::connect(format!("127.0.0.1:{}", port))
+                    .await
+                    .is_ok()
+                {
+                    connection_failures = 0;
+                    continue;
+                }
+                connection_failures += 1;
+                if connection_failures < MAX_CONNECTION_FAILURES {
+                    continue;
+                }
+                "stopped accepting connections".to_string()
+            }
+            (None, None) => continue,
+        };
+        connection_failures = 0;
+
+        if !restart(&client, &state, generation, &reason, &mut backoff).await {
+            return;
+        }
+    }
+}
+
+/// Restart the IDE server with exponential backoff, returning whether it is running again
+async fn restart(
+    client: &Client,
+    state: &Arc<Mutex<ServerState>>,
+    generation: u64,
+    reason: &str,
+    backoff: &mut Backoff,
+) -> bool {
+    backoff.reset_if_stable();
+
+    // Make sure a hung process does not keep its port
+    let process = {
+        let mut state = state.lock().await;
+        if state.ide_server.generation != generation {
+            return false;
+        }
+        state.ide_server.client = None;
+        state.ide_server.process.take()
+    };
+    if let Some(child) = process
+        && let Err(e) = process::stop_ide_server(child).await
+    {
+        client
+            .log_message(
+                MessageType::WARNING,
+                format!("Failed to stop IDE server: {}", e),
+            )
+            .await;
+    }
+
+    report_status(
+        client,
+        IdeServerStatus::Restarting,
+        None,
+        Some(format!("PureScript IDE server {}, restarting", reason)),
+    )
+    .await;
+
+    while let Some(delay) = backoff.next_delay() {
+        tokio::time::sleep(delay).await;
+
+        let (working_dir, config) = {
+            let state = state.lock().await;
+            if state.ide_server.generation != generation {
+                return false;
+            }
+            (state.ide_server.working_dir.clone(), state.config.clone())
+        };
+        let (Some(working_dir), Some(config)) = (working_dir, config) else {
+            return false;
+        };
+
+        let started =
+            process::start_ide_server_async(&working_dir, &config.output_dir, &config.source_globs)
+                .await;
+
+        let (child, port) = match started {
+            Ok(started) => started,
+            Err(e) => {
+                client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!(
+                            "IDE server restart attempt {} failed: {}",
+                            backoff.failures, e
+                        ),
+                    )
+                    .await;
+                continue;
+            }
+        };
+
+        let ide = IdeClient::new(port);
+        let replaced = {
+            let mut state = state.lock().await;
+            // The server was replaced or stopped while this one was starting
+            if state.ide_server.generation != generation {
+                Some(child)
+            } else {
+                state.ide_server.process = Some(child);
+                state.ide_server.client = Some(ide.clone());
+                // Cached hints may come from the previous server
+                state.local_inlay_hints.clear();
+                None
+            }
+        };
+        if let Some(child) = replaced {
+            let _ = process::stop_ide_server(child).await;
+            return false;
+        }
+        backoff.started();
+
+        if let Err(e) = ide_commands::load_all(&ide).await {
+            client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("Failed to reload modules after restart: {}", e),
+                )
+                .await;
+        }
+
+        report_status(
+            client,
+            IdeServerStatus::Running,
+            Some(port),
+            Some("PureScript IDE server restarted".to_string()),
+        )
+        .await;
+        return true;
+    }
+
+    report_status(
+        client,
+        IdeServerStatus::Failed,
+        None,
+        Some(format!(
+            "PureScript IDE server could not be restarted after {} attempts; \
+             run the purescript.restartIdeServer command or change the configuration \
+             to try again",
+            MAX_RESTART_ATTEMPTS
+        )),
+    )
+    .await;
+    false
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_backoff_doubles_up_to_the_maximum() {
+        let mut backoff = Backoff::new();
+        let delays: Vec<_> = std::iter::from_fn(|| backoff.next_delay())
+            .map(|delay| delay.as_secs())
+            .collect();
+        assert_eq!(delays, vec![1, 2, 4, 8, 16, 32]);
+        assert_eq!(backoff.next_delay(), None);
+    }
+
+    #[test]
+    fn test_backoff_persists_across_unstable_restarts() {
+        let mut backoff = Backoff::new();
+        backoff.next_delay();
+        backoff.started();
+
+        backoff.reset_if_stable();
+        assert_eq!(backoff.failures, 1);
+        assert_eq!(backoff.next_delay(), Some(Duration::from_secs(2)));
+    }
+
+    #[test]
+    fn test_backoff_resets_after_stable_uptime() {
+        let mut backoff = Backoff::new();
+        backoff.next_delay();
+        backoff.next_delay();
+        backoff.started_at = Instant::now() - STABLE_UPTIME;
+
+        backoff.reset_if_stable();
+        assert_eq!(backoff.failures, 0);
+        assert_eq!(backoff.next_delay(), Some(INITIAL_BACKOFF));
+    }
+}
@@ -0,0 +1,309 @@
+//! Commands and results checked against exchanges recorded from `purs ide server`
+
+use super::*;
+use serde_json::Value;
+
+macro_rules! fixture {
+    ($name:literal) => {
+        serde_json::from_str::<Value>(include_str!(concat!(
+            env!("CARGO_MANIFEST_DIR"),
+            "/tests/fixtures/ide/",
+            $name,
+            ".json"
+        )))
+        .unwrap()
+    };
+}
+
+fn assert_request(command: IdeCommand, recorded: &Value) {
+    assert_eq!(serde_json::to_value(&command).unwrap(), recorded["request"]);
+}
+
+fn result<T: serde::de::DeserializeOwned>(recorded: &Value) -> T {
+    serde_json::from_value(recorded["response"]["result"].clone()).unwrap()
+}
+
+#[test]
+fn load() {
+    let recorded = fixture!("load");
+    assert_request(IdeCommand::Load { modules: vec![] }, &recorded);
+    assert_eq!(
+        result::<String>(&recorded),
+        "Loaded 212 modules and 0 warnings"
+    );
+}
+
+#[test]
+fn type_search() {
+    let recorded = fixture!("type");
+    assert_request(
+        IdeCommand::Type {
+            search: "fromMaybe".to_string(),
+            filters: vec![
+                Filter::Exact("fromMaybe".to_string()),
+                Filter::Modules(vec!["Data.Maybe".to_string()]),
+            ],
+            current_module: Some("Main".to_string()),
+        },
+        &recorded,
+    );
+
+    let infos: Vec<CompletionInfo> = result(&recorded);
+    assert_eq!(infos.len(), 1);
+    assert_eq!(infos[0].module, "Data.Maybe");
+    assert_eq!(infos[0].type_info, "forall a. a -> Maybe a -> a");
+    assert_eq!(infos[0].namespace(), Namespace::Value);
+    let defined_at = infos[0].defined_at.as_ref().unwrap();
+    assert_eq!(defined_at.start, [230, 1]);
+}
+
+#[test]
+fn complete() {
+    let recorded = fixture!("complete");
+    assert_request(
+        IdeCommand::Complete {
+            filters: vec![
+                Filter::Prefix("ma".to_string()),
+                Filter::Dependencies {
+                    qualifier: None,
+                    module_text: "module Main where\nimport Data.Array (mapMaybe)\n".to_string(),
+                },
+            ],
+            matcher: None,
+            current_module: Some("Main".to_string()),
+            options: CompleteOptions {
+                max_results: Some(50),
+                group_reexports: true,
+            },
+        },
+        &recorded,
+    );
+
+    let infos: Vec<CompletionInfo> = result(&recorded);
+    assert_eq!(infos[0].identifier, "mapMaybe");
+    assert_eq!(infos[0].expanded_type, None);
+    assert_eq!(infos[0].documentation, None);
+}
+
+#[test]
+fn complete_with_matcher() {
+    let recorded = fixture!("complete_flex");
+    assert_request(
+        IdeCommand::Complete {
+            filters: vec![],
+            matcher: Some(Matcher::Flex {
+                search: "mkp".to_string(),
+            }),
+            current_module: None,
+            options: CompleteOptions {
+                max_results: Some(100),
+                group_reexports: true,
+            },
+        },
+        &recorded,
+    );
+
+    let infos: Vec<CompletionInfo> = result(&recorded);
+    assert_eq!(infos[0].identifier, "makePerson");
+}
+
+#[test]
+fn case_split() {
+    let recorded = fixture!("case_split");
+    assert_request(
+        IdeCommand::CaseSplit {
+            line: "describe m = ?describe".to_string(),
+            begin: 9,
+            end: 10,
+            annotations: false,
+            type_info: "Maybe String".to_string(),
+        },
+        &recorded,
+    );
+    assert_eq!(
+        result::<Vec<String>>(&recorded),
+        [
+            "describe Nothing = ?describe",
+            "describe (Just _) = ?describe"
+        ]
+    );
+}
+
+#[test]
+fn add_clause() {
+    let recorded = fixture!("add_clause");
+    assert_request(
+        IdeCommand::AddClause {
+            line: "greet :: String -> Int -> String".to_string(),
+            annotations: false,
+        },
+        &recorded,
+    );
+    assert_eq!(result::<Vec<String>>(&recorded)[1], "greet _ _ = ?greet");
+}
+
+#[test]
+fn import() {
+    let recorded = fixture!("import");
+    assert_request(
+        IdeCommand::Import {
+            file: "/project/src/Main.purs".to_string(),
+            outfile: None,
+            filters: vec![Filter::Modules(vec!["Data.Maybe".to_string()])],
+            import_command: ImportCommand::Identifier {
+                identifier: "fromMaybe".to_string(),
+                qualifier: None,
+            },
+        },
+        &recorded,
+    );
+
+    let ImportResult::Lines(lines) = result(&recorded) else {
+        panic!("expected the rewritten file");
+    };
+    assert!(lines.contains(&"import Data.Maybe (fromMaybe)".to_string()));
+}
+
+#[test]
+fn import_qualified() {
+    let recorded = fixture!("import_qualified");
+    assert_request(
+        IdeCommand::Import {
+            file: "/project/src/Main.purs".to_string(),
+            outfile: None,
+            filters: vec![],
+            import_command: ImportCommand::Qualified {
+                module: "Data.Map".to_string(),
+                qualifier: "Map".to_string(),
+            },
+        },
+        &recorded,
+    );
+    assert!(matches!(result(&recorded), ImportResult::Lines(_)));
+}
+
+#[test]
+fn import_ambiguous() {
+    let recorded = fixture!("import_ambiguous");
+    let ImportResult::Ambiguous(candidates) = result(&recorded) else {
+        panic!("expected candidates");
+    };
+    let modules: Vec<&str> = candidates.iter().map(|info| info.module.as_str()).collect();
+    assert_eq!(modules, ["Data.Array", "Data.List"]);
+}
+
+#[test]
+fn list_modules() {
+    let recorded = fixture!("list_loaded_modules");
+    assert_request(IdeCommand::List(ListType::LoadedModules), &recorded);
+    assert!(result::<Vec<String>>(&recorded).contains(&"Main".to_string()));
+
+    let recorded = fixture!("list_available_modules");
+    assert_request(IdeCommand::List(ListType::AvailableModules), &recorded);
+    assert!(result::<Vec<String>>(&recorded).contains(&"Test".to_string()));
+}
+
+#[test]
+fn list_imports() {
+    let recorded = fixture!("list_imports");
+    assert_request(
+        IdeCommand::List(ListType::Import {
+            file: "/project/src/Main.purs".to_string(),
+        }),
+        &recorded,
+    );
+
+    let imports: ModuleImports = result(&recorded);
+    assert_eq!(imports.module_name.as_deref(), Some("Main"));
+    assert_eq!(imports.imports.len(), 4);
+    assert_eq!(imports.imports[0].import_type, ImportType::Implicit);
+    assert_eq!(
+        imports.imports[1].import_type,
+        ImportType::Explicit {
+            identifiers: vec!["Maybe(..)".to_string(), "fromMaybe".to_string()]
+        }
+    );
+    assert_eq!(imports.imports[2].qualifier.as_deref(), Some("Map"));
+    assert_eq!(
+        imports.imports[3].import_type,
+        ImportType::Hiding {
+            identifiers: vec!["head".to_string()]
+        }
+    );
+}
+
+#[test]
+fn usages() {
+    let recorded = fixture!("usages");
+    assert_request(
+        IdeCommand::Usages {
+            module: "Main".to_string(),
+            namespace: Namespace::Value,
+            identifier: "greet".to_string(),
+        },
+        &recorded,
+    );
+
+    let spans: Vec<ErrorSpan> = result(&recorded);
+    assert_eq!(spans[1].name, "/project/src/Test.purs");
+    assert_eq!(spans[1].end, [8, 13]);
+}
+
+#[test]
+fn rebuild() {
+    let recorded = fixture!("rebuild");
+    assert_request(
+        IdeCommand::Rebuild {
+            file: "data:module Main where\n\nx = 1\n".to_string(),
+            actual_file: Some("/project/src/Main.purs".to_string()),
+            codegen: Some(vec![CodegenTarget::Js, CodegenTarget::Corefn]),
+        },
+        &recorded,
+    );
+
+    let warnings: Vec<RebuildError> = result(&recorded);
+    assert_eq!(warnings[0].error_code, "MissingTypeDeclaration");
+    assert_eq!(
+        warnings[0].suggestion.as_ref().unwrap().replacement,
+        "x :: Int\n"
+    );
+    assert_eq!(warnings[0].inferred_type().as_deref(), Some("Int"));
+}
+
+#[test]
+fn rebuild_with_errors() {
+    let recorded = fixture!("rebuild_error");
+    assert_request(
+        IdeCommand::Rebuild {
+            file: "/project/src/Main.purs".to_string(),
+            actual_file: None,
+            codegen: None,
+        },
+        &recorded,
+    );
+    assert_eq!(recorded["response"]["resultType"], "error");
+
+    let errors: Vec<RebuildError> = result(&recorded);
+    let hole = errors[0].hole_completions.as_ref().unwrap();
+    assert_eq!(hole.name, "value");
+    assert_eq!(hole.completions[0].identifier, "x");
+    assert_eq!(errors[0].inferred_type().as_deref(), Some("Int"));
+}
+
+#[test]
+fn commands_without_params() {
+    for (command, name, expected) in [
+        (IdeCommand::Cwd, "cwd", "/project"),
+        (IdeCommand::Reset, "reset", "State has been reset."),
+        (IdeCommand::Quit, "quit", "Bye bye!"),
+    ] {
+        let recorded = match name {
+            "cwd" => fixture!("cwd"),
+            "reset" => fixture!("reset"),
+            _ => fixture!("quit"),
+        };
+        assert_eq!(command.name(), name);
+        assert_request(command, &recorded);
+        assert_eq!(result::<String>(&recorded), expected);
+    }
+}
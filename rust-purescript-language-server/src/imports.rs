@@ -0,0 +1,136 @@
+use crate::ide_server::{Filter, IdeClient, ImportCommand, commands as ide_commands};
+use anyhow::Result;
+use lsp_types::{Position, Range, TextEdit};
+use std::sync::atomic::{AtomicU64, Ordering};
+
+static TEMP_FILE_COUNTER: AtomicU64 = AtomicU64::new(0);
+
+/// Compute the edits that apply an import command to in-memory document content
+///
+/// The IDE server's `import` command only reads files from disk, so the content is
+/// written to a temporary file first. This keeps the edits correct for unsaved buffers.
+pub async fn import_edits(
+    ide: &IdeClient,
+    content: &str,
+    import_command: &ImportCommand,
+    filters: &[Filter],
+) -> Result<Vec<TextEdit>> {
+    let temp_path = std::env::temp_dir().join(format!(
+        "purescript-lsp-import-{}-{}.purs",
+        std::process::id(),
+        TEMP_FILE_COUNTER.fetch_add(1, Ordering::Relaxed)
+    ));
+    tokio::fs::write(&temp_path, content).await?;
+
+    let result = match temp_path.to_str() {
+        Some(path) => ide_commands::import(ide, path, import_command, filters).await,
+        None => Err(anyhow::anyhow!("Temporary file path is not valid UTF-8")),
+    };
+    let _ = tokio::fs::remove_file(&temp_path).await;
+
+    Ok(diff_lines(content, &result?).into_iter().collect())
+}
+
+/// Create a single edit replacing the lines that differ between two versions of a file
+fn diff_lines(original: &str, rewritten: &[String]) -> Option<TextEdit> {
+    let original: Vec<&str> = original.lines().collect();
+
+    let prefix = original
+        .iter()
+        .zip(rewritten)
+        .take_while(|(a, b)| **a == b.as_str())
+        .count();
+    let suffix = original[prefix..]
+        .iter()
+        .rev()
+        .zip(rewritten[prefix..].iter().rev())
+        .take_while(|(a, b)| **a == b.as_str())
+        .count();
+
+    let removed_end = original.len() - suffix;
+    let inserted = &rewritten[prefix..rewritten.len() - suffix];
+
+    if prefix == removed_end && inserted.is_empty() {
+        return None;
+    }
+
+    let new_text = inserted
+        .iter()
+        .map(|line| format!("{}\n", line))
+        .collect::<String>();
+
+    Some(TextEdit {
+        range: Range {
+            start: Position {
+                line: prefix as u32,
+                character: 0,
+            },
+            end: Position {
+                line: removed_end as u32,
+                character: 0,
+            },
+        },
+        new_text,
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const MODULE: &str =
+        "module Main where\n\nimport Prelude\nimport Effect.Console (log)\n\nmain = log \"hi\"\n";
+
+    fn rewritten(replace: &str, with: &str) -> Vec<String> {
+        MODULE
+            .replace(replace, with)
+            .lines()
+            .map(String::from)
+            .collect()
+    }
+
+    fn range(start: u32, end: u32) -> Range {
+        Range {
+            start: Position {
+                line: start,
+                character: 0,
+            },
+            end: Position {
+                line: end,
+                character: 0,
+            },
+        }
+    }
+
+    #[test]
+    fn inserted_import_line() {
+        let lines = rewritten(
+            "import Prelude\n",
+            "import Prelude\nimport Data.Maybe (Maybe(..))\n",
+        );
+        assert_eq!(
+            diff_lines(MODULE, &lines),
+            Some(TextEdit {
+                range: range(3, 3),
+                new_text: "import Data.Maybe (Maybe(..))\n".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn extended_import_line() {
+        let lines = rewritten("(log)", "(log, logShow)");
+        assert_eq!(
+            diff_lines(MODULE, &lines),
+            Some(TextEdit {
+                range: range(3, 4),
+                new_text: "import Effect.Console (log, logShow)\n".to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn unchanged_file() {
+        assert_eq!(diff_lines(MODULE, &rewritten("", "")), None);
+    }
+}
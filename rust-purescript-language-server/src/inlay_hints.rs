@@ -0,0 +1,462 @@
+use crate::config::InlayHintsConfig;
+use crate::document;
+use crate::ide_server::{IdeClient, RebuildError, commands as ide_commands};
+use crate::types::ServerState;
+use anyhow::Result;
+use lsp_types::{InlayHint, InlayHintKind, InlayHintLabel, Position, Range, TextEdit};
+use std::collections::{HashMap, HashSet};
+use tokio::sync::Mutex;
+use tower_lsp::Client;
+
+/// Show the inferred types of unannotated top-level bindings
+///
+/// The compiler reports these as `MissingTypeDeclaration` warnings whose suggestion
+/// is the full signature. The hint is shown after the binding's name, and accepting
+/// it inserts the signature above the binding.
+pub fn missing_signature_hints(
+    errors: &[RebuildError],
+    content: &str,
+    range: Range,
+) -> Vec<InlayHint> {
+    let lines: Vec<&str> = content.lines().collect();
+
+    errors
+        .iter()
+        .filter(|error| error.error_code == "MissingTypeDeclaration")
+        .filter_map(|error| {
+            let signature = error.suggestion.as_ref()?.replacement.trim();
+            let (name, signature_type) = signature.split_once("::")?;
+            let line = error.position.start_line.checked_sub(1)?;
+            if line < range.start.line || line > range.end.line {
+                return None;
+            }
+
+            // The hint goes right after the name of the binding
+            let start_column = error.position.start_column.saturating_sub(1);
+            let (_, name_end) = document::find_word(lines.get(line as usize)?, name.trim())
+                .into_iter()
+                .find(|(start, _)| *start >= start_column)?;
+
+            // Long inferred types are split over several lines in the suggestion
+            let signature_type = signature_type.split_whitespace().collect::<Vec<_>>();
+
+            Some(InlayHint {
+                position: Position {
+                    line,
+                    character: name_end,
+                },
+                label: InlayHintLabel::String(format!(":: {}", signature_type.join(" "))),
+                kind: Some(InlayHintKind::TYPE),
+                text_edits: Some(vec![TextEdit {
+                    range: Range {
+                        start: Position { line, character: 0 },
+                        end: Position { line, character: 0 },
+                    },
+                    new_text: format!("{}\n", signature),
+                }]),
+                tooltip: None,
+                padding_left: Some(true),
+                padding_right: None,
+                data: None,
+            })
+        })
+        .collect()
+}
+
+/// Keywords that can start an indented line without it being a binding
+const KEYWORDS: &[&str] = &[
+    "ado", "case", "do", "else", "if", "in", "let", "of", "then", "where",
+];
+
+/// A wildcard type added to the document to learn the type of a local binding
+struct WildcardAnnotation {
+    /// 1-based line and column of the `_` in the annotated document
+    wildcard: (u32, u32),
+    /// Where the hint goes in the original document
+    position: Position,
+}
+
+/// Show the inferred types of `let`/`where` bindings and lambda parameters
+///
+/// The IDE server only knows the types of top-level declarations, so the document
+/// is typechecked with wildcard annotations (`x :: _` and `\(x :: _) ->`) added to
+/// the unannotated local bindings. The compiler reports the type inferred for each
+/// wildcard as a `WildcardInferredType` warning. The IDE server is left with the
+/// original document loaded if `keep_content_loaded` is set (as fast rebuilds on
+/// change do) and with the file on disk otherwise, and no output is written. A
+/// document that does not compile gets no hints.
+pub async fn local_binding_hints(
+    ide: &IdeClient,
+    file_path: &str,
+    content: &str,
+    options: &InlayHintsConfig,
+    keep_content_loaded: bool,
+) -> Result<Vec<InlayHint>> {
+    let (annotated, annotations) = annotate_local_bindings(content, options);
+    if annotations.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let original = keep_content_loaded.then_some(content);
+    let errors = ide_commands::rebuild_scratch(ide, file_path, &annotated, original).await?;
+    let inferred: HashMap<(u32, u32), String> = errors
+        .iter()
+        .filter(|error| error.error_code == "WildcardInferredType")
+        .filter_map(|error| {
+            let wildcard = (error.position.start_line, error.position.start_column);
+            Some((wildcard, error.inferred_type()?))
+        })
+        .collect();
+
+    Ok(annotations
+        .iter()
+        .filter_map(|annotation| {
+            let inferred_type = inferred.get(&annotation.wildcard)?;
+            Some(InlayHint {
+                position: annotation.position,
+                label: InlayHintLabel::String(format!(":: {}", inferred_type)),
+                kind: Some(InlayHintKind::TYPE),
+                text_edits: None,
+                tooltip: None,
+                padding_left: Some(true),
+                padding_right: None,
+                data: None,
+            })
+        })
+        .collect())
+}
+
+/// Add wildcard annotations to the unannotated local bindings of a document
+fn annotate_local_bindings(
+    content: &str,
+    options: &InlayHintsConfig,
+) -> (String, Vec<WildcardAnnotation>) {
+    let binding_re =
+        regex::Regex::new(r"^(\s*)((?:let|where)\s+)?([a-z_][A-Za-z0-9_']*)(.*)$").unwrap();
+
+    let mut lines: Vec<String> = Vec::new();
+    let mut annotations = Vec::new();
+    let mut in_instance = false;
+    let mut nesting = Nesting::default();
+    // Names that are annotated (or already given a wildcard) in the current declaration
+    let mut annotated: HashSet<(usize, String)> = HashSet::new();
+
+    for (line_number, line) in content.lines().enumerate() {
+        if !line.starts_with(char::is_whitespace) && !line.trim().is_empty() {
+            // Instance members cannot be given wildcard signatures
+            in_instance = ["instance", "else", "derive", "class"]
+                .iter()
+                .any(|keyword| line.starts_with(keyword));
+            annotated.clear();
+            nesting.depth = 0;
+        }
+        // Lines continuing a bracket are record fields or arguments, not bindings
+        let in_brackets = nesting.depth > 0 || nesting.in_block_comment;
+        nesting.scan(line);
+
+        let (mut line, lambda_annotations) = if options.lambda_parameters {
+            annotate_lambda_parameters(line)
+        } else {
+            (line.to_string(), vec![])
+        };
+
+        if let Some(captures) = binding_re.captures(&line).filter(|captures| {
+            options.let_bindings
+                && !in_instance
+                && !in_brackets
+                && !captures[1].is_empty()
+                && !KEYWORDS.contains(&&captures[3])
+        }) {
+            let name = captures[3].to_string();
+            let name_start = captures.get(3).unwrap().start();
+            let column = line[..name_start].chars().count();
+            let key = (column, name.clone());
+            let is_signature = captures[4].trim_start().starts_with("::");
+            let needs_annotation =
+                !is_signature && !annotated.contains(&key) && is_binding_rest(&captures[4]);
+
+            if is_signature || needs_annotation {
+                annotated.insert(key);
+            }
+            if needs_annotation {
+                // `let x = 1` becomes `let x :: _` followed by `x = 1` aligned under it
+                let signature = format!("{}{} :: _", &line[..name_start], name);
+                annotations.push(WildcardAnnotation {
+                    wildcard: (lines.len() as u32 + 1, signature.chars().count() as u32),
+                    position: Position {
+                        line: line_number as u32,
+                        character: (column + name.chars().count()) as u32,
+                    },
+                });
+                line = format!("{}{}", " ".repeat(column), &line[name_start..]);
+                lines.push(signature);
+            }
+        }
+
+        for (hint_column, wildcard_column) in lambda_annotations {
+            annotations.push(WildcardAnnotation {
+                wildcard: (lines.len() as u32 + 1, wildcard_column + 1),
+                position: Position {
+                    line: line_number as u32,
+                    character: hint_column,
+                },
+            });
+        }
+        lines.push(line);
+    }
+
+    (lines.join("\n"), annotations)
+}
+
+/// Brackets and block comments left open at the end of the lines scanned so far
+#[derive(Default)]
+struct Nesting {
+    depth: u32,
+    in_block_comment: bool,
+}
+
+impl Nesting {
+    /// Update the nesting with a line, skipping string and char literals and comments
+    fn scan(&mut self, line: &str) {
+        let chars: Vec<char> = line.chars().collect();
+        let mut index = 0;
+
+        while index < chars.len() {
+            let c = chars[index];
+            let next = chars.get(index + 1).copied();
+
+            if self.in_block_comment {
+                if c == '-' && next == Some('}') {
+                    self.in_block_comment = false;
+                    index += 1;
+                }
+            } else if c == '{' && next == Some('-') {
+                self.in_block_comment = true;
+                index += 1;
+            } else if c == '-' && next == Some('-') {
+                break;
+            } else if c == '"' {
+                index += 1;
+                while index < chars.len() && chars[index] != '"' {
+                    if chars[index] == '\\' {
+                        index += 1;
+                    }
+                    index += 1;
+                }
+            } else if c == '\'' && chars.get(index + 2) == Some(&'\'') {
+                index += 2;
+            } else if "([{".contains(c) {
+                self.depth += 1;
+            } else if ")]}".contains(c) {
+                self.depth = self.depth.saturating_sub(1);
+            }
+            index += 1;
+        }
+    }
+}
+
+/// Check the text after a name is the rest of a binding: arguments, then `=`
+fn is_binding_rest(rest: &str) -> bool {
+    let chars: Vec<char> = rest.chars().collect();
+    let Some(equals) = (0..chars.len()).find(|&index| {
+        chars[index] == '='
+            && (index == 0 || !document::is_operator_char(chars[index - 1]))
+            && chars
+                .get(index + 1)
+                .is_none_or(|c| !document::is_operator_char(*c))
+    }) else {
+        return false;
+    };
+
+    let arguments: String = chars[..equals].iter().collect();
+    !["::", "<-", "->", "\\", "\"", "`"]
+        .iter()
+        .any(|token| arguments.contains(token))
+}
+
+/// Wrap the simple parameters of lambdas on a line in wildcard annotations
+///
+/// Returns the new line and, for each parameter, the column its hint goes at in
+/// the original line and the 0-based column of its `_` in the new line.
+fn annotate_lambda_parameters(line: &str) -> (String, Vec<(u32, u32)>) {
+    let chars: Vec<char> = line.chars().collect();
+    let mut annotated = String::new();
+    let mut annotated_len = 0u32;
+    let mut annotations = Vec::new();
+    let mut index = 0;
+
+    let mut push = |annotated: &mut String, text: &str| {
+        annotated.push_str(text);
+        annotated_len += text.chars().count() as u32;
+        annotated_len
+    };
+
+    while index < chars.len() {
+        let c = chars[index];
+
+        if c == '"' {
+            // Copy string literals untouched
+            let start = index;
+            index += 1;
+            while index < chars.len() && chars[index] != '"' {
+                if chars[index] == '\\' {
+                    index += 1;
+                }
+                index += 1;
+            }
+            index = (index + 1).min(chars.len());
+            push(
+                &mut annotated,
+                &chars[start..index].iter().collect::<String>(),
+            );
+            continue;
+        }
+        if c == '-' && chars.get(index + 1) == Some(&'-') {
+            push(&mut annotated, &chars[index..].iter().collect::<String>());
+            break;
+        }
+
+        let is_lambda = c == '\\'
+            && (index == 0 || !document::is_operator_char(chars[index - 1]))
+            && chars
+                .get(index + 1)
+                .is_none_or(|c| !document::is_operator_char(*c));
+        push(&mut annotated, &c.to_string());
+        index += 1;
+        if !is_lambda {
+            continue;
+        }
+
+        // Parameters up to `->`; anything but a plain name ends the annotations
+        loop {
+            while index < chars.len() && chars[index].is_whitespace() {
+                push(&mut annotated, " ");
+                index += 1;
+            }
+            let start = index;
+            while index < chars.len()
+                && (chars[index].is_alphanumeric() || "_'".contains(chars[index]))
+            {
+                index += 1;
+            }
+            let name: String = chars[start..index].iter().collect();
+            if name.is_empty()
+                || name == "_"
+                || !name.starts_with(|c: char| c.is_lowercase() || c == '_')
+            {
+                push(&mut annotated, &name);
+                break;
+            }
+
+            let wildcard_column = push(&mut annotated, &format!("({} :: ", name));
+            push(&mut annotated, "_)");
+            annotations.push((index as u32, wildcard_column));
+        }
+    }
+
+    (annotated, annotations)
+}
+
+/// Ask the client to re-request inlay hints after the stored errors changed
+pub async fn refresh(client: &Client, state: &Mutex<ServerState>) {
+    let supported = state.lock().await.inlay_hint_refresh_support;
+    if supported {
+        let _ = client.inlay_hint_refresh().await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Wildcard locations in the annotated document and hint positions
+    type Annotations = Vec<((u32, u32), Position)>;
+
+    fn annotate(
+        content: &str,
+        let_bindings: bool,
+        lambda_parameters: bool,
+    ) -> (String, Annotations) {
+        let options = InlayHintsConfig {
+            top_level: true,
+            let_bindings,
+            lambda_parameters,
+        };
+        let (annotated, annotations) = annotate_local_bindings(content, &options);
+        (
+            annotated,
+            annotations
+                .into_iter()
+                .map(|annotation| (annotation.wildcard, annotation.position))
+                .collect(),
+        )
+    }
+
+    fn position(line: u32, character: u32) -> Position {
+        Position { line, character }
+    }
+
+    #[test]
+    fn let_binding() {
+        let (annotated, annotations) =
+            annotate("main = do\n  let x = 1\n  log (show x)", true, false);
+        assert_eq!(
+            annotated,
+            "main = do\n  let x :: _\n      x = 1\n  log (show x)"
+        );
+        assert_eq!(annotations, vec![((2, 12), position(1, 7))]);
+    }
+
+    #[test]
+    fn where_binding_with_arguments() {
+        let (annotated, annotations) = annotate("f a = g a\n  where\n  g b = b", true, false);
+        assert_eq!(annotated, "f a = g a\n  where\n  g :: _\n  g b = b");
+        assert_eq!(annotations, vec![((3, 8), position(2, 3))]);
+    }
+
+    #[test]
+    fn annotated_bindings_are_skipped() {
+        let content = "f = y\n  where\n  y :: Int\n  y = 1";
+        let (annotated, annotations) = annotate(content, true, false);
+        assert_eq!(annotated, content);
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn record_fields_are_not_bindings() {
+        let content = "f p = p\n  { name = \"x\"\n  , age = 1\n  }\n\ng p = p {\n    name = \"(\",\n    age = 1\n  }\n  where\n  y = 1";
+        let (annotated, annotations) = annotate(content, true, false);
+        // Only the `where` binding after the record update is annotated
+        assert_eq!(annotations, vec![((11, 8), position(10, 3))]);
+        assert!(annotated.contains("    name = \"(\",\n    age = 1\n"));
+    }
+
+    #[test]
+    fn commented_out_bindings_are_skipped() {
+        let content = "f = 1\n  {-\n  x = 2\n  -}\n  where\n  y = 1";
+        let (_, annotations) = annotate(content, true, false);
+        assert_eq!(annotations, vec![((6, 8), position(5, 3))]);
+    }
+
+    #[test]
+    fn instance_members_are_skipped() {
+        let content = "instance showX :: Show X where\n  show x = \"X\"";
+        let (annotated, annotations) = annotate(content, true, false);
+        assert_eq!(annotated, content);
+        assert!(annotations.is_empty());
+    }
+
+    #[test]
+    fn lambda_parameters() {
+        let (annotated, annotations) = annotate("f = map \\x y -> x", false, true);
+        assert_eq!(annotated, "f = map \\(x :: _) (y :: _) -> x");
+        assert_eq!(
+            annotations,
+            vec![((1, 16), position(0, 10)), ((1, 25), position(0, 12))]
+        );
+
+        let (annotated, annotations) = annotate("f = map \\x -> x", false, false);
+        assert_eq!(annotated, "f = map \\x -> x");
+        assert!(annotations.is_empty());
+    }
+}
@@ -4,12 +4,23 @@ use tower_lsp::{LspService, Server};
 mod build;
 mod code_actions;
 mod commands;
+mod completion;
 mod config;
+mod definition;
+mod diagnostic_store;
 mod diagnostics;
+mod document;
 mod formatting;
+mod hover;
 mod ide_server;
+mod imports;
+mod inlay_hints;
 mod ragu;
+mod references;
+mod rename;
 mod server;
+mod signature_help;
+mod symbols;
 mod types;
 
 use server::Backend;
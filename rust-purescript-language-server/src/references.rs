@@ -0,0 +1,79 @@
+use crate::definition;
+use crate::document::{self, Identifier};
+use crate::ide_server::{CompletionInfo, IdeClient, Namespace, commands as ide_commands};
+use anyhow::Result;
+use lsp_types::{Location, Position};
+
+/// Resolve the identifier at a position to the declaration it refers to
+///
+/// A type and a data constructor can share a name, so only declarations in the
+/// namespace of the position (a type or an expression) are considered.
+pub async fn resolve_declaration(
+    ide: &IdeClient,
+    content: &str,
+    position: Position,
+) -> Result<Option<(Identifier, CompletionInfo)>> {
+    let Some(identifier) = document::identifier_at(content, position) else {
+        return Ok(None);
+    };
+
+    let results = ide_commands::type_search_in_module(
+        ide,
+        content,
+        identifier.qualifier.as_deref(),
+        &identifier.name,
+    )
+    .await?;
+
+    let namespace = if document::is_type_position(content, position) {
+        Namespace::Type
+    } else {
+        Namespace::Value
+    };
+
+    Ok(results
+        .into_iter()
+        .find(|info| info.namespace() == namespace)
+        .map(|info| (identifier, info)))
+}
+
+/// Find all references to the declaration at a position
+///
+/// Usages cover every module the IDE server has loaded from the project's source
+/// globs. The declaration itself is only included when `include_declaration` is set,
+/// in which case it comes first. The IDE server can report the declaration site as a
+/// usage too, so usages starting where the declaration does are left out.
+pub async fn find_references(
+    ide: &IdeClient,
+    workspace_root: &str,
+    content: &str,
+    position: Position,
+    include_declaration: bool,
+) -> Result<Vec<Location>> {
+    let Some((_, info)) = resolve_declaration(ide, content, position).await? else {
+        return Ok(vec![]);
+    };
+
+    let spans = ide_commands::usages(ide, &info.module, info.namespace(), &info.identifier).await?;
+
+    let declaration = info
+        .defined_at
+        .as_ref()
+        .and_then(|span| definition::span_to_location(span, workspace_root));
+
+    let mut locations: Vec<Location> = spans
+        .iter()
+        .filter_map(|span| definition::span_to_location(span, workspace_root))
+        .filter(|location| {
+            declaration.as_ref().is_none_or(|declaration| {
+                location.uri != declaration.uri || location.range.start != declaration.range.start
+            })
+        })
+        .collect();
+
+    if include_declaration && let Some(declaration) = declaration {
+        locations.insert(0, declaration);
+    }
+
+    Ok(locations)
+}
@@ -0,0 +1,378 @@
+use crate::build;
+use crate::config::Config;
+use crate::document;
+use crate::ide_server::{
+    CompletionInfo, ErrorSpan, IdeClient, Namespace, commands as ide_commands,
+};
+use crate::references;
+use anyhow::Result;
+use lsp_types::{Position, Range, TextEdit, Url, WorkspaceEdit};
+use std::collections::HashMap;
+
+/// Keywords that can never be used as value names
+const KEYWORDS: &[&str] = &[
+    "ado", "case", "class", "data", "derive", "do", "else", "false", "forall", "foreign", "if",
+    "import", "in", "infix", "infixl", "infixr", "instance", "let", "module", "newtype", "of",
+    "then", "true", "type", "where",
+];
+
+/// Check the declaration at a position can be renamed, returning the range to rename
+pub async fn prepare_rename(
+    ide: &IdeClient,
+    workspace_root: &str,
+    config: &Config,
+    content: &str,
+    position: Position,
+) -> Result<Option<Range>> {
+    let Some((identifier, info)) = references::resolve_declaration(ide, content, position).await?
+    else {
+        return Ok(None);
+    };
+
+    check_renamable(&info, workspace_root, config)?;
+
+    Ok(Some(identifier.range))
+}
+
+/// Rename a top-level declaration across every module of the project
+///
+/// Usages come from the IDE server. Declaration sites, the defining module's export
+/// list and explicit import lists are found by scanning the project's source files.
+/// `open_documents` takes precedence over files on disk so unsaved edits are respected.
+pub async fn rename(
+    ide: &IdeClient,
+    workspace_root: &str,
+    config: &Config,
+    content: &str,
+    position: Position,
+    new_name: &str,
+    open_documents: &HashMap<Url, String>,
+) -> Result<Option<WorkspaceEdit>> {
+    let Some((_, info)) = references::resolve_declaration(ide, content, position).await? else {
+        return Ok(None);
+    };
+
+    check_renamable(&info, workspace_root, config)?;
+    check_new_name(&info, new_name)?;
+
+    let mut files = SourceFiles {
+        open_documents,
+        loaded: HashMap::new(),
+    };
+    let mut edits: HashMap<Url, Vec<TextEdit>> = HashMap::new();
+    let mut add_edit = |uri: &Url, line: u32, (start, end): (u32, u32)| {
+        let edit = TextEdit {
+            range: Range {
+                start: Position {
+                    line,
+                    character: start,
+                },
+                end: Position {
+                    line,
+                    character: end,
+                },
+            },
+            new_text: new_name.to_string(),
+        };
+        let file_edits = edits.entry(uri.clone()).or_default();
+        if !file_edits.contains(&edit) {
+            file_edits.push(edit);
+        }
+    };
+
+    // Usages, including qualified ones such as `Map.insert`
+    let usages =
+        ide_commands::usages(ide, &info.module, info.namespace(), &info.identifier).await?;
+    for span in &usages {
+        let Some(uri) = build::file_path_to_uri(&span.name, workspace_root) else {
+            continue;
+        };
+        if let Some(occurrence) = files.find_in_span(&uri, span, &info.identifier) {
+            add_edit(&uri, span.start[0].saturating_sub(1), occurrence);
+        }
+    }
+
+    // Declaration sites and the export list of the defining module
+    if let Some(span) = &info.defined_at
+        && let Some(uri) = build::file_path_to_uri(&span.name, workspace_root)
+        && let Some(text) = files.get(&uri)
+    {
+        for (line, occurrence) in declaration_occurrences(&text, span, &info) {
+            add_edit(&uri, line, occurrence);
+        }
+        for (line, occurrence) in export_list_occurrences(&text, &info) {
+            add_edit(&uri, line, occurrence);
+        }
+    }
+
+    // Explicit import lists in every project module
+    let mut project_uris: Vec<Url> = config
+        .project_files(workspace_root)
+        .iter()
+        .filter_map(|path| Url::from_file_path(path).ok())
+        .collect();
+    project_uris.extend(open_documents.keys().cloned());
+    project_uris.sort();
+    project_uris.dedup();
+
+    for uri in &project_uris {
+        let Some(text) = files.get(uri) else {
+            continue;
+        };
+        for (line, occurrence) in import_list_occurrences(&text, &info) {
+            add_edit(uri, line, occurrence);
+        }
+    }
+
+    Ok(Some(WorkspaceEdit {
+        changes: Some(edits),
+        document_changes: None,
+        change_annotations: None,
+    }))
+}
+
+/// Refuse to rename declarations that live outside the project's own sources
+fn check_renamable(info: &CompletionInfo, workspace_root: &str, config: &Config) -> Result<()> {
+    if matches!(
+        info.declaration_type.as_deref(),
+        Some("valueoperator") | Some("typeoperator") | Some("module")
+    ) {
+        anyhow::bail!("Renaming operators and modules is not supported");
+    }
+
+    let Some(span) = &info.defined_at else {
+        anyhow::bail!(
+            "Cannot rename '{}': its definition location is unknown",
+            info.identifier
+        );
+    };
+
+    if !config.is_project_file(workspace_root, &span.name) {
+        anyhow::bail!(
+            "Cannot rename '{}': it is defined in {} ({}), outside the project's source globs",
+            info.identifier,
+            info.module,
+            span.name
+        );
+    }
+
+    Ok(())
+}
+
+/// Check the new name is a valid identifier for the kind of declaration being renamed
+fn check_new_name(info: &CompletionInfo, new_name: &str) -> Result<()> {
+    let capitalized = matches!(
+        info.declaration_type.as_deref(),
+        Some("type") | Some("synonym") | Some("typeclass") | Some("dataconstructor")
+    );
+
+    let pattern = if capitalized {
+        r"^[A-Z][A-Za-z0-9_']*$"
+    } else {
+        r"^[a-z_][A-Za-z0-9_']*$"
+    };
+
+    if !regex::Regex::new(pattern).unwrap().is_match(new_name) {
+        anyhow::bail!(
+            "'{}' is not a valid name for {}: it must start with {} letter",
+            new_name,
+            info.identifier,
+            if capitalized {
+                "an uppercase"
+            } else {
+                "a lowercase"
+            }
+        );
+    }
+
+    if KEYWORDS.contains(&new_name) {
+        anyhow::bail!("'{}' is a reserved keyword", new_name);
+    }
+
+    Ok(())
+}
+
+/// Source text of project files, preferring open documents over the file on disk
+struct SourceFiles<'a> {
+    open_documents: &'a HashMap<Url, String>,
+    loaded: HashMap<Url, Option<String>>,
+}
+
+impl SourceFiles<'_> {
+    fn get(&mut self, uri: &Url) -> Option<String> {
+        if let Some(text) = self.open_documents.get(uri) {
+            return Some(text.clone());
+        }
+
+        self.loaded
+            .entry(uri.clone())
+            .or_insert_with(|| {
+                uri.to_file_path()
+                    .ok()
+                    .and_then(|path| std::fs::read_to_string(path).ok())
+            })
+            .clone()
+    }
+
+    /// Find the identifier within a usage span (the last occurrence, skipping any qualifier)
+    fn find_in_span(
+        &mut self,
+        uri: &Url,
+        span: &ErrorSpan,
+        identifier: &str,
+    ) -> Option<(u32, u32)> {
+        let text = self.get(uri)?;
+        let line = text.lines().nth(span.start[0].saturating_sub(1) as usize)?;
+
+        let start = span.start[1].saturating_sub(1);
+        let end = if span.end[0] == span.start[0] {
+            span.end[1].saturating_sub(1)
+        } else {
+            u32::MAX
+        };
+
+        document::find_word(line, identifier)
+            .into_iter()
+            .rev()
+            .find(|(word_start, word_end)| *word_start >= start && *word_end <= end)
+    }
+}
+
+/// Find where the declaration itself names the identifier
+///
+/// This is the first occurrence on the line the declaration starts on. For values
+/// it also includes the type signature and every clause (top-level lines starting
+/// with the name, including `foreign import`s).
+fn declaration_occurrences(
+    text: &str,
+    span: &ErrorSpan,
+    info: &CompletionInfo,
+) -> Vec<(u32, (u32, u32))> {
+    let declaration_line = span.start[0].saturating_sub(1);
+    let mut occurrences: Vec<(u32, (u32, u32))> = text
+        .lines()
+        .nth(declaration_line as usize)
+        .and_then(|line| {
+            let start = span.start[1].saturating_sub(1);
+            document::find_word(line, &info.identifier)
+                .into_iter()
+                .find(|(word_start, _)| *word_start >= start)
+        })
+        .map(|occurrence| vec![(declaration_line, occurrence)])
+        .unwrap_or_default();
+
+    if info.declaration_type.as_deref() == Some("value") {
+        occurrences.extend(text.lines().enumerate().filter_map(|(line_number, line)| {
+            let rest = line
+                .strip_prefix("foreign import")
+                .map(str::trim_start)
+                .unwrap_or(line);
+            let column = line[..line.len() - rest.len()].chars().count() as u32;
+            document::find_word(line, &info.identifier)
+                .into_iter()
+                .find(|(start, _)| *start == column)
+                .map(|occurrence| (line_number as u32, occurrence))
+        }));
+    }
+
+    occurrences
+}
+
+/// Check whether an occurrence in an import or export list names the declaration,
+/// rather than a type or data constructor that shares its name
+///
+/// Types are listed directly and constructors inside `Type(..)`, as decided by
+/// `document::is_type_position`. Lowercase names are always values.
+fn in_namespace(text: &str, line: u32, start: u32, info: &CompletionInfo) -> bool {
+    if !info.identifier.starts_with(char::is_uppercase) {
+        return true;
+    }
+
+    let in_type = document::is_type_position(
+        text,
+        Position {
+            line,
+            character: start,
+        },
+    );
+    in_type == (info.namespace() == Namespace::Type)
+}
+
+/// Find the identifier in the module's export list (between the module name and `where`)
+fn export_list_occurrences(text: &str, info: &CompletionInfo) -> Vec<(u32, (u32, u32))> {
+    let mut occurrences = Vec::new();
+    let module_re = regex::Regex::new(r"^module\s+[A-Z][A-Za-z0-9_'.]*").unwrap();
+
+    let mut in_header = false;
+    for (line_number, line) in text.lines().enumerate() {
+        let skip = match module_re.find(line) {
+            Some(module) => {
+                in_header = true;
+                line[..module.end()].chars().count() as u32
+            }
+            None => 0,
+        };
+        if !in_header {
+            continue;
+        }
+
+        let code = line.split("--").next().unwrap_or(line);
+        occurrences.extend(
+            document::find_word(code, &info.identifier)
+                .into_iter()
+                .filter(|(start, _)| {
+                    *start >= skip && in_namespace(text, line_number as u32, *start, info)
+                })
+                .map(|occurrence| (line_number as u32, occurrence)),
+        );
+
+        if !document::find_word(code, "where").is_empty() {
+            break;
+        }
+    }
+
+    occurrences
+}
+
+/// Find the identifier in explicit import lists of the defining (or a re-exporting) module
+fn import_list_occurrences(text: &str, info: &CompletionInfo) -> Vec<(u32, (u32, u32))> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut occurrences = Vec::new();
+
+    for import in document::parse_imports(text) {
+        if import.module != info.module && !info.exported_from.contains(&import.module) {
+            continue;
+        }
+
+        // Only look inside the import list, which starts at the first parenthesis
+        let mut in_list = false;
+        for line_number in import.start_line..=import.end_line {
+            let Some(line) = lines.get(line_number as usize) else {
+                break;
+            };
+            let code = line.split("--").next().unwrap_or(line);
+            let list_start = if in_list {
+                0
+            } else {
+                match code.find('(') {
+                    Some(index) => {
+                        in_list = true;
+                        code[..index].chars().count() as u32
+                    }
+                    None => continue,
+                }
+            };
+
+            occurrences.extend(
+                document::find_word(code, &info.identifier)
+                    .into_iter()
+                    .filter(|(start, _)| {
+                        *start > list_start && in_namespace(text, line_number, *start, info)
+                    })
+                    .map(|occurrence| (line_number, occurrence)),
+            );
+        }
+    }
+
+    occurrences
+}
@@ -1,15 +1,27 @@
 use crate::code_actions;
 use crate::commands;
+use crate::completion;
 use crate::config;
+use crate::definition;
+use crate::diagnostic_store::DiagnosticSource;
 use crate::diagnostics;
 use crate::formatting;
-use crate::ide_server::{commands as ide_commands, process};
+use crate::hover;
+use crate::ide_server::client::IdeClientError;
+use crate::ide_server::supervisor::{self, IdeServerStatus};
+use crate::ide_server::{IdeClient, commands as ide_commands, process};
+use crate::inlay_hints;
+use crate::references;
+use crate::rename;
+use crate::signature_help;
+use crate::symbols;
 use crate::types::ServerState;
 use lsp_types::{
     ProgressParams, ProgressParamsValue, WorkDoneProgress, WorkDoneProgressBegin,
     WorkDoneProgressCreateParams, WorkDoneProgressEnd, notification::Progress,
     request::WorkDoneProgressCreate,
 };
+use std::path::Path;
 use std::sync::Arc;
 use tokio::sync::Mutex;
 use tower_lsp::jsonrpc::Result as LspResult;
@@ -43,31 +55,80 @@ impl Backend {
         // Log the configuration
         config::log_config(&self.client, &config).await;
 
-        // Start the IDE server
-        let (process, port) = process::start_ide_server_async(
-            workspace_root,
-            &config.output_dir,
-            &config.source_globs,
-        )
-        .await?;
+        // Attach to the configured IDE server, or start our own
+        let (ide, process) = match &config.external_ide_server {
+            Some(external) => {
+                let port = external.port(workspace_root)?;
+                let (ide, cwd) = process::attach_ide_server(port).await?;
+                if Path::new(&cwd) != Path::new(workspace_root) {
+                    self.client
+                        .show_message(
+                            MessageType::WARNING,
+                            format!(
+                                "The PureScript IDE server on port {} serves {}, not this workspace",
+                                port, cwd
+                            ),
+                        )
+                        .await;
+                }
+                (ide, None)
+            }
+            None => {
+                let (process, port) = process::start_ide_server_async(
+                    workspace_root,
+                    &config.output_dir,
+                    &config.source_globs,
+                )
+                .await?;
+                (IdeClient::new(port), Some(process))
+            }
+        };
+        let port = ide.port();
+        let attached = process.is_none();
 
         // Update state
-        let mut state = self.state.lock().await;
-        state.config = Some(config);
-        state.workspace_root = Some(workspace_root.to_string());
-        state.ide_server.port = Some(port);
-        state.ide_server.process = Some(process);
-        state.ide_server.working_dir = Some(workspace_root.to_string());
+        let generation = {
+            let mut state = self.state.lock().await;
+            state.config = Some(config);
+            state.workspace_root = Some(workspace_root.to_string());
+            state.ide_server.client = Some(ide);
+            state.ide_server.process = process;
+            state.ide_server.working_dir = Some(workspace_root.to_string());
+            state.ide_server.generation
+        };
 
-        self.client
-            .log_message(MessageType::INFO, format!("Purescript IDE port {}", port))
-            .await;
+        if attached {
+            self.client
+                .log_message(
+                    MessageType::INFO,
+                    format!(
+                        "Attached to external Purescript IDE server on port {}",
+                        port
+                    ),
+                )
+                .await;
+        } else {
+            self.client
+                .log_message(MessageType::INFO, format!("Purescript IDE port {}", port))
+                .await;
+        }
+        supervisor::report_status(&self.client, IdeServerStatus::Running, Some(port), None).await;
+
+        // Restart the IDE server whenever it dies, until it is replaced or stopped.
+        // An attached server belongs to whoever started it and is left alone.
+        if !attached {
+            tokio::spawn(supervisor::supervise(
+                self.client.clone(),
+                self.state.clone(),
+                generation,
+            ));
+        }
 
         Ok(())
     }
 
-    /// Restart the IDE server (used when configuration changes)
-    async fn restart_server(&self) -> anyhow::Result<()> {
+    /// Restart the IDE server (used when configuration changes or on request)
+    async fn restart_server(&self, reason: &str) -> anyhow::Result<()> {
         let workspace_root = {
             let state = self.state.lock().await;
             state.workspace_root.clone()
@@ -77,18 +138,25 @@ impl Backend {
             self.client
                 .log_message(
                     MessageType::INFO,
-                    "Configuration changed, restarting IDE server...".to_string(),
+                    format!("{}, restarting IDE server...", reason),
                 )
                 .await;
 
             // Stop the current IDE server
-            let mut process = {
+            let process = {
                 let mut state = self.state.lock().await;
-                state.ide_server.process.take()
+                state.ide_server.take_process()
             };
 
-            if let Some(ref mut child) = process {
-                let _ = child.kill();
+            if let Some(child) = process
+                && let Err(e) = process::stop_ide_server(child).await
+            {
+                self.client
+                    .log_message(
+                        MessageType::WARNING,
+                        format!("Failed to stop IDE server: {}", e),
+                    )
+                    .await;
             }
 
             // Reinitialize with new config
@@ -98,11 +166,32 @@ impl Backend {
         Ok(())
     }
 
+    /// Log a failed request to the IDE server
+    ///
+    /// An unreachable or unresponsive server, usually one that is restarting or busy
+    /// rebuilding, is only a warning; an error answered by the server is an error.
+    async fn log_ide_error(&self, message: &str, e: &anyhow::Error) {
+        if e.downcast_ref::<IdeClientError>()
+            .is_some_and(IdeClientError::is_unavailable)
+        {
+            self.client
+                .log_message(
+                    MessageType::WARNING,
+                    format!("{}, IDE server unavailable: {}", message, e),
+                )
+                .await;
+        } else {
+            self.client
+                .log_message(MessageType::ERROR, format!("{}: {}", message, e))
+                .await;
+        }
+    }
+
     /// Trigger fast rebuild for a file
     /// If content is provided, it will use the data: prefix format for in-memory rebuild
     async fn trigger_fast_rebuild(
         &self,
-        port: u16,
+        ide: &IdeClient,
         file_path: &str,
         uri: &Url,
         content: Option<String>,
@@ -113,11 +202,15 @@ impl Backend {
             .and_then(|name| name.to_str())
             .unwrap_or("file");
 
-        // Create unique token using counter to avoid collisions
-        let token = {
+        // Create unique token using counter to avoid collisions, and take the generation
+        // of this rebuild's results before it starts
+        let (token, generation) = {
             let mut state = self.state.lock().await;
             state.rebuild_counter += 1;
-            NumberOrString::String(format!("purescript-rebuild-{}", state.rebuild_counter))
+            (
+                NumberOrString::String(format!("purescript-rebuild-{}", state.rebuild_counter)),
+                state.diagnostics.next_generation(),
+            )
         };
 
         // Request client to create progress indicator
@@ -154,7 +247,7 @@ impl Backend {
             .await;
 
         let result =
-            ide_commands::rebuild_file_with_content(port, file_path, content.as_deref()).await;
+            ide_commands::rebuild_file_with_content(ide, file_path, content.as_deref()).await;
 
         // Send end notification
         self.client
@@ -168,36 +261,24 @@ impl Backend {
 
         match result {
             Ok(rebuild_result) => {
-                // Convert rebuild errors to diagnostics
-                if let Some(errors) = rebuild_result.errors {
-                    let diagnostics = diagnostics::convert_rebuild_errors(&errors, uri);
-
-                    // Store errors in state for code actions
-                    {
-                        let mut state = self.state.lock().await;
-                        state.document_errors.insert(uri.clone(), errors.clone());
-                    }
+                // Store errors in state for code actions; a newer rebuild may have finished first
+                let stored = {
+                    let mut state = self.state.lock().await;
+                    state.diagnostics.set(
+                        uri,
+                        DiagnosticSource::Rebuild,
+                        generation,
+                        rebuild_result.errors.unwrap_or_default(),
+                    )
+                };
 
-                    if !diagnostics.is_empty() {
-                        self.client
-                            .publish_diagnostics(uri.clone(), diagnostics, None)
-                            .await;
-                    }
-                } else {
-                    // Clear diagnostics and errors for this file since there are no errors
-                    {
-                        let mut state = self.state.lock().await;
-                        state.document_errors.remove(uri);
-                    }
-                    self.client
-                        .publish_diagnostics(uri.clone(), vec![], None)
-                        .await;
+                if stored {
+                    diagnostics::publish(&self.client, &self.state, uri).await;
+                    inlay_hints::refresh(&self.client, &self.state).await;
                 }
             }
             Err(e) => {
-                self.client
-                    .log_message(MessageType::ERROR, format!("Fast rebuild failed: {}", e))
-                    .await;
+                self.log_ide_error("Fast rebuild failed", &e).await;
             }
         }
     }
@@ -205,17 +286,17 @@ impl Backend {
     /// Handle document focus event - triggers rebuild when fast_rebuild_on_change is enabled
     pub async fn handle_document_focus(&self, uri: &Url) {
         // Get the document content and check if fast rebuild is enabled
-        let (fast_rebuild_enabled, port, content) = {
+        let (fast_rebuild_enabled, ide, content) = {
             let state = self.state.lock().await;
             (
                 state.fast_rebuild_on_change(),
-                state.ide_server.port,
+                state.ide_server.client.clone(),
                 state.document_contents.get(uri).cloned(),
             )
         };
 
         if fast_rebuild_enabled {
-            if let Some(port) = port {
+            if let Some(ide) = ide {
                 if let Some(content) = content {
                     if let Ok(file_path) = uri.to_file_path() {
                         if let Some(file_path_str) = file_path.to_str() {
@@ -223,7 +304,7 @@ impl Backend {
                             // (fast rebuild from content doesn't work with foreign modules)
                             if !content.contains("foreign import") {
                                 // Pass the content for data: prefix rebuild
-                                self.trigger_fast_rebuild(port, file_path_str, uri, Some(content))
+                                self.trigger_fast_rebuild(&ide, file_path_str, uri, Some(content))
                                     .await;
                             }
                         }
@@ -232,11 +313,73 @@ impl Backend {
             }
         }
     }
+    /// Compute the edits of deferred code actions, dropping those without any
+    async fn resolve_code_actions_eagerly(
+        &self,
+        ide: &IdeClient,
+        content: &str,
+        actions: Vec<CodeAction>,
+    ) -> Vec<CodeAction> {
+        let mut resolved = Vec::new();
+        for mut action in actions {
+            let Some(data) = action
+                .data
+                .take()
+                .and_then(|data| serde_json::from_value::<code_actions::ResolveData>(data).ok())
+            else {
+                resolved.push(action);
+                continue;
+            };
+
+            // Skip actions the IDE server cannot apply (e.g. ambiguous imports)
+            match code_actions::resolve_code_action(ide, content, &data).await {
+                Ok(edit)
+                    if edit
+                        .changes
+                        .as_ref()
+                        .is_some_and(|changes| changes.values().any(|edits| !edits.is_empty())) =>
+                {
+                    action.edit = Some(edit);
+                    resolved.push(action);
+                }
+                Ok(_) => {}
+                Err(e) => {
+                    self.client
+                        .log_message(
+                            MessageType::LOG,
+                            format!("Dropping code action '{}': {}", action.title, e),
+                        )
+                        .await;
+                }
+            }
+        }
+        resolved
+    }
 }
 
 #[tower_lsp::async_trait]
 impl LanguageServer for Backend {
     async fn initialize(&self, params: InitializeParams) -> LspResult<InitializeResult> {
+        // Only ask the client to refresh inlay hints if it supports it
+        let inlay_hint_refresh_support = params
+            .capabilities
+            .workspace
+            .as_ref()
+            .and_then(|workspace| workspace.inlay_hint.as_ref())
+            .and_then(|inlay_hint| inlay_hint.refresh_support)
+            .unwrap_or(false);
+        self.state.lock().await.inlay_hint_refresh_support = inlay_hint_refresh_support;
+
+        // Code action edits that need the IDE server are deferred if the client can resolve them
+        let code_action_resolve_support = params
+            .capabilities
+            .text_document
+            .as_ref()
+            .and_then(|text_document| text_document.code_action.as_ref())
+            .and_then(|code_action| code_action.resolve_support.as_ref())
+            .is_some_and(|resolve| resolve.properties.iter().any(|property| property == "edit"));
+        self.state.lock().await.code_action_resolve_support = code_action_resolve_support;
+
         // Store workspace root but don't initialize yet - wait for initialized notification
         if let Some(workspace_root) = params.root_uri.and_then(|uri| uri.to_file_path().ok()) {
             if let Some(root_str) = workspace_root.to_str() {
@@ -251,12 +394,43 @@ impl LanguageServer for Backend {
                     TextDocumentSyncKind::FULL,
                 )),
                 document_formatting_provider: Some(OneOf::Left(true)),
-                code_action_provider: Some(CodeActionProviderCapability::Simple(true)),
+                hover_provider: Some(HoverProviderCapability::Simple(true)),
+                definition_provider: Some(OneOf::Left(true)),
+                references_provider: Some(OneOf::Left(true)),
+                document_symbol_provider: Some(OneOf::Left(true)),
+                workspace_symbol_provider: Some(OneOf::Left(true)),
+                inlay_hint_provider: Some(OneOf::Left(true)),
+                rename_provider: Some(OneOf::Right(RenameOptions {
+                    prepare_provider: Some(true),
+                    work_done_progress_options: Default::default(),
+                })),
+                completion_provider: Some(CompletionOptions {
+                    trigger_characters: Some(vec![".".to_string()]),
+                    resolve_provider: Some(true),
+                    ..Default::default()
+                }),
+                signature_help_provider: Some(SignatureHelpOptions {
+                    trigger_characters: Some(vec![" ".to_string(), "(".to_string()]),
+                    retrigger_characters: None,
+                    work_done_progress_options: Default::default(),
+                }),
+                code_action_provider: Some(CodeActionProviderCapability::Options(
+                    CodeActionOptions {
+                        code_action_kinds: Some(vec![
+                            CodeActionKind::QUICKFIX,
+                            CodeActionKind::REFACTOR,
+                            CodeActionKind::REFACTOR_REWRITE,
+                        ]),
+                        resolve_provider: Some(true),
+                        ..Default::default()
+                    },
+                )),
                 execute_command_provider: Some(ExecuteCommandOptions {
                     commands: vec![
                         "purescript.build".to_string(),
                         "purescript.buildQuick".to_string(),
                         "purescript.focusDocument".to_string(),
+                        "purescript.restartIdeServer".to_string(),
                     ],
                     ..Default::default()
                 }),
@@ -297,56 +471,103 @@ impl LanguageServer for Backend {
                         .await;
                 }
             }
-        } else {
-            // Already initialized - check if config actually changed
-            let new_client_config = config::fetch_client_config(&self.client).await;
+        } else if let Some(root) = workspace_root {
+            // Already initialized - resolve the new config and see what actually changed
+            let client_config = config::fetch_client_config(&self.client).await;
+            let new_config = match config::init_with_client_config(&root, client_config) {
+                Ok(new_config) => new_config,
+                Err(e) => {
+                    self.client
+                        .log_message(
+                            MessageType::ERROR,
+                            format!("Failed to load configuration: {}", e),
+                        )
+                        .await;
+                    return;
+                }
+            };
 
-            let current_client_config = {
+            // A server that could not be restarted is started again whatever changed
+            let (current_config, ide_available) = {
                 let state = self.state.lock().await;
-                state.config.as_ref().map(|c| config::ClientConfig {
-                    formatter: Some(c.formatter.clone()),
-                    fast_rebuild_on_save: Some(c.fast_rebuild_on_save),
-                    fast_rebuild_on_change: Some(c.fast_rebuild_on_change),
-                })
+                (state.config.clone(), state.ide_server.client.is_some())
             };
-
-            if new_client_config != current_client_config {
-                self.client
-                    .log_message(
-                        MessageType::INFO,
-                        "Client configuration changed, restarting IDE server",
-                    )
-                    .await;
-
-                if let Err(e) = self.restart_server().await {
+            match current_config {
+                Some(current_config) if ide_available && current_config == new_config => {
                     self.client
                         .log_message(
-                            MessageType::ERROR,
-                            format!("Failed to restart server after configuration change: {}", e),
+                            MessageType::INFO,
+                            "Configuration unchanged, skipping restart",
                         )
                         .await;
                 }
-            } else {
-                self.client
-                    .log_message(
-                        MessageType::INFO,
-                        "Configuration unchanged, skipping restart",
-                    )
-                    .await;
+                Some(current_config)
+                    if ide_available && !current_config.needs_restart(&new_config) =>
+                {
+                    self.client
+                        .log_message(
+                            MessageType::INFO,
+                            "Configuration changed, applying it without restarting the IDE server",
+                        )
+                        .await;
+                    config::log_config(&self.client, &new_config).await;
+
+                    let inlay_hints_changed = current_config.inlay_hints != new_config.inlay_hints;
+                    let diagnostics_changed = current_config.diagnostics != new_config.diagnostics;
+                    let files = {
+                        let mut state = self.state.lock().await;
+                        state.config = Some(new_config);
+                        if inlay_hints_changed {
+                            state.local_inlay_hints.clear();
+                        }
+                        state.diagnostics.files()
+                    };
+                    if inlay_hints_changed {
+                        inlay_hints::refresh(&self.client, &self.state).await;
+                    }
+
+                    // Publish the stored errors and warnings again under the new rules
+                    if diagnostics_changed {
+                        for uri in &files {
+                            diagnostics::publish(&self.client, &self.state, uri).await;
+                        }
+                    }
+                }
+                _ => {
+                    if let Err(e) = self.restart_server("Configuration changed").await {
+                        self.client
+                            .log_message(
+                                MessageType::ERROR,
+                                format!(
+                                    "Failed to restart server after configuration change: {}",
+                                    e
+                                ),
+                            )
+                            .await;
+                    }
+                }
             }
         }
     }
 
     async fn shutdown(&self) -> LspResult<()> {
         // Take the process from state to get ownership
-        let mut process = {
+        let (process, ide) = {
             let mut state = self.state.lock().await;
-            state.ide_server.process.take()
+            (
+                state.ide_server.take_process(),
+                state.ide_server.client.clone(),
+            )
         };
 
-        // Kill the process if it exists
-        if let Some(ref mut child) = process {
-            match child.kill() {
+        // Stop the process if we started it; an attached IDE server keeps running
+        if let Some(child) = process {
+            // Let the server exit on its own first; killing it covers a hung server
+            if let Some(ide) = ide {
+                let _ = ide_commands::quit(&ide).await;
+            }
+
+            match process::stop_ide_server(child).await {
                 Ok(_) => {
                     self.client
                         .log_message(MessageType::INFO, "PureScript IDE server stopped")
@@ -377,20 +598,23 @@ impl LanguageServer for Backend {
         }
 
         // Trigger fast rebuild on open when fast_rebuild_on_change is enabled
-        let (fast_rebuild_enabled, port) = {
+        let (fast_rebuild_enabled, ide) = {
             let state = self.state.lock().await;
-            (state.fast_rebuild_on_change(), state.ide_server.port)
+            (
+                state.fast_rebuild_on_change(),
+                state.ide_server.client.clone(),
+            )
         };
 
         if fast_rebuild_enabled {
-            if let Some(port) = port {
+            if let Some(ide) = ide {
                 if let Ok(file_path) = uri.to_file_path() {
                     if let Some(file_path_str) = file_path.to_str() {
                         // Skip rebuild if file contains foreign imports
                         // (fast rebuild from content doesn't work with foreign modules)
                         if !content.contains("foreign import") {
                             // Pass the content for data: prefix rebuild
-                            self.trigger_fast_rebuild(port, file_path_str, uri, Some(content))
+                            self.trigger_fast_rebuild(&ide, file_path_str, uri, Some(content))
                                 .await;
                         }
                     }
@@ -409,23 +633,27 @@ impl LanguageServer for Backend {
             {
                 let mut state = self.state.lock().await;
                 state.document_contents.insert(uri.clone(), content.clone());
+                state.diagnostics.content_changed(uri);
             }
 
             // Optionally trigger fast rebuild on change using data: prefix
-            let (fast_rebuild_enabled, port) = {
+            let (fast_rebuild_enabled, ide) = {
                 let state = self.state.lock().await;
-                (state.fast_rebuild_on_change(), state.ide_server.port)
+                (
+                    state.fast_rebuild_on_change(),
+                    state.ide_server.client.clone(),
+                )
             };
 
             if fast_rebuild_enabled {
-                if let Some(port) = port {
+                if let Some(ide) = ide {
                     if let Ok(file_path) = uri.to_file_path() {
                         if let Some(file_path_str) = file_path.to_str() {
                             // Skip rebuild if file contains foreign imports
                             // (fast rebuild from content doesn't work with foreign modules)
                             if !content.contains("foreign import") {
                                 // Pass the content for data: prefix rebuild
-                                self.trigger_fast_rebuild(port, file_path_str, uri, Some(content))
+                                self.trigger_fast_rebuild(&ide, file_path_str, uri, Some(content))
                                     .await;
                             }
                         }
@@ -439,17 +667,20 @@ impl LanguageServer for Backend {
         let uri = &params.text_document.uri;
 
         // Get state values and immediately drop the lock
-        let (fast_rebuild_enabled, port) = {
+        let (fast_rebuild_enabled, ide) = {
             let state = self.state.lock().await;
-            (state.fast_rebuild_on_save(), state.ide_server.port)
+            (
+                state.fast_rebuild_on_save(),
+                state.ide_server.client.clone(),
+            )
         }; // Lock is dropped here
 
         if fast_rebuild_enabled {
-            if let Some(port) = port {
+            if let Some(ide) = ide {
                 if let Ok(file_path) = uri.to_file_path() {
                     if let Some(file_path_str) = file_path.to_str() {
                         // For saves, rebuild from disk (no content passed)
-                        self.trigger_fast_rebuild(port, file_path_str, uri, None)
+                        self.trigger_fast_rebuild(&ide, file_path_str, uri, None)
                             .await;
                     } else {
                         self.client
@@ -466,7 +697,7 @@ impl LanguageServer for Backend {
                 }
             } else {
                 self.client
-                    .log_message(MessageType::ERROR, "IDE server port not available")
+                    .log_message(MessageType::ERROR, "IDE server not available")
                     .await;
             }
         }
@@ -479,8 +710,12 @@ impl LanguageServer for Backend {
         {
             let mut state = self.state.lock().await;
             state.document_contents.remove(uri);
-            state.document_errors.remove(uri);
+            state.diagnostics.remove(uri, DiagnosticSource::Rebuild);
+            state.local_inlay_hints.remove(uri);
         }
+
+        // Fall back to the results of the last full build, if any
+        diagnostics::publish(&self.client, &self.state, uri).await;
     }
 
     async fn formatting(
@@ -530,20 +765,395 @@ impl LanguageServer for Backend {
         }
     }
 
-    async fn code_action(&self, params: CodeActionParams) -> LspResult<Option<CodeActionResponse>> {
-        // Clone errors and immediately drop the lock to avoid deadlock
-        let errors = {
+    async fn hover(&self, params: HoverParams) -> LspResult<Option<Hover>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+
+        // Get IDE client and document content, then immediately drop the lock
+        let (ide, document_content) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.document_contents.get(uri).cloned(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(content)) = (ide, document_content) else {
+            return Ok(None);
+        };
+
+        match hover::hover(
+            &ide,
+            &content,
+            params.text_document_position_params.position,
+        )
+        .await
+        {
+            Ok(hover) => Ok(hover),
+            Err(e) => {
+                self.log_ide_error("Hover failed", &e).await;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn signature_help(
+        &self,
+        params: SignatureHelpParams,
+    ) -> LspResult<Option<SignatureHelp>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+
+        // Get IDE client and document content, then immediately drop the lock
+        let (ide, document_content) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.document_contents.get(uri).cloned(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(content)) = (ide, document_content) else {
+            return Ok(None);
+        };
+
+        match signature_help::signature_help(
+            &ide,
+            &content,
+            params.text_document_position_params.position,
+        )
+        .await
+        {
+            Ok(help) => Ok(help),
+            Err(e) => {
+                self.log_ide_error("Signature help failed", &e).await;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn inlay_hint(&self, params: InlayHintParams) -> LspResult<Option<Vec<InlayHint>>> {
+        let uri = &params.text_document.uri;
+
+        // Get the document's merged errors, content, hint options, IDE client, cached
+        // local hints and whether edits stay loaded, then immediately drop the lock
+        let (errors, document_content, options, ide, cached_local_hints, fast_rebuild_enabled) = {
+            let state = self.state.lock().await;
+            (
+                state.diagnostics.errors(uri),
+                state.document_contents.get(uri).cloned(),
+                state.inlay_hints(),
+                state.ide_server.client.clone(),
+                state.local_inlay_hints.get(uri).cloned(),
+                state.fast_rebuild_on_change(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(content), Some(options)) = (document_content, options) else {
+            return Ok(None);
+        };
+
+        let mut hints = if options.top_level {
+            inlay_hints::missing_signature_hints(&errors, &content, params.range)
+        } else {
+            vec![]
+        };
+
+        // Local hints need a rebuild, so they are cached until the content changes
+        // (fast rebuild from content doesn't work with foreign modules)
+        if (options.let_bindings || options.lambda_parameters)
+            && !content.contains("foreign import")
+            && let Some(ide) = ide
+            && let Ok(file_path) = uri.to_file_path()
+            && let Some(file_path_str) = file_path.to_str()
+        {
+            let local_hints = match cached_local_hints {
+                Some((cached_content, local_hints)) if cached_content == content => local_hints,
+                _ => {
+                    match inlay_hints::local_binding_hints(
+                        &ide,
+                        file_path_str,
+                        &content,
+                        &options,
+                        fast_rebuild_enabled,
+                    )
+                    .await
+                    {
+                        Ok(local_hints) => {
+                            let mut state = self.state.lock().await;
+                            state
+                                .local_inlay_hints
+                                .insert(uri.clone(), (content.clone(), local_hints.clone()));
+                            local_hints
+                        }
+                        Err(e) => {
+                            self.log_ide_error("Local inlay hints failed", &e).await;
+                            vec![]
+                        }
+                    }
+                }
+            };
+
+            hints.extend(local_hints.into_iter().filter(|hint| {
+                hint.position.line >= params.range.start.line
+                    && hint.position.line <= params.range.end.line
+            }));
+        }
+
+        Ok(Some(hints))
+    }
+
+    async fn goto_definition(
+        &self,
+        params: GotoDefinitionParams,
+    ) -> LspResult<Option<GotoDefinitionResponse>> {
+        let uri = &params.text_document_position_params.text_document.uri;
+
+        // Get IDE client, workspace root and document content, then immediately drop the lock
+        let (ide, workspace_root, document_content) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.workspace_root.clone(),
+                state.document_contents.get(uri).cloned(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(workspace_root), Some(content)) =
+            (ide, workspace_root, document_content)
+        else {
+            return Ok(None);
+        };
+
+        match definition::goto_definition(
+            &ide,
+            &workspace_root,
+            &content,
+            params.text_document_position_params.position,
+        )
+        .await
+        {
+            Ok(location) => Ok(location.map(GotoDefinitionResponse::Scalar)),
+            Err(e) => {
+                self.log_ide_error("Go to definition failed", &e).await;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn references(&self, params: ReferenceParams) -> LspResult<Option<Vec<Location>>> {
+        let uri = &params.text_document_position.text_document.uri;
+
+        // Get IDE client, workspace root and document content, then immediately drop the lock
+        let (ide, workspace_root, document_content) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.workspace_root.clone(),
+                state.document_contents.get(uri).cloned(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(workspace_root), Some(content)) =
+            (ide, workspace_root, document_content)
+        else {
+            return Ok(None);
+        };
+
+        match references::find_references(
+            &ide,
+            &workspace_root,
+            &content,
+            params.text_document_position.position,
+            params.context.include_declaration,
+        )
+        .await
+        {
+            Ok(locations) => Ok(Some(locations)),
+            Err(e) => {
+                self.log_ide_error("Find references failed", &e).await;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn document_symbol(
+        &self,
+        params: DocumentSymbolParams,
+    ) -> LspResult<Option<DocumentSymbolResponse>> {
+        // Get document content and immediately drop the lock
+        let document_content = {
             let state = self.state.lock().await;
             state
-                .document_errors
+                .document_contents
                 .get(&params.text_document.uri)
                 .cloned()
-                .unwrap_or_default()
         }; // Lock is dropped here
 
-        if errors.is_empty() {
-            return Ok(Some(vec![]));
+        Ok(document_content
+            .map(|content| DocumentSymbolResponse::Nested(symbols::document_symbols(&content))))
+    }
+
+    async fn symbol(
+        &self,
+        params: WorkspaceSymbolParams,
+    ) -> LspResult<Option<Vec<SymbolInformation>>> {
+        // Get IDE client and workspace root, then immediately drop the lock
+        let (ide, workspace_root) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.workspace_root.clone(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(workspace_root)) = (ide, workspace_root) else {
+            return Ok(None);
+        };
+
+        match symbols::workspace_symbols(&ide, &workspace_root, &params.query).await {
+            Ok(symbols) => Ok(Some(symbols)),
+            Err(e) => {
+                self.log_ide_error("Workspace symbols failed", &e).await;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn prepare_rename(
+        &self,
+        params: TextDocumentPositionParams,
+    ) -> LspResult<Option<PrepareRenameResponse>> {
+        // Get IDE client, workspace root, config and document content, then immediately drop the lock
+        let (ide, workspace_root, config, document_content) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.workspace_root.clone(),
+                state.config.clone(),
+                state
+                    .document_contents
+                    .get(&params.text_document.uri)
+                    .cloned(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(workspace_root), Some(config), Some(content)) =
+            (ide, workspace_root, config, document_content)
+        else {
+            return Ok(None);
+        };
+
+        rename::prepare_rename(&ide, &workspace_root, &config, &content, params.position)
+            .await
+            .map(|range| range.map(PrepareRenameResponse::Range))
+            .map_err(|e| tower_lsp::jsonrpc::Error::invalid_params(e.to_string()))
+    }
+
+    async fn rename(&self, params: RenameParams) -> LspResult<Option<WorkspaceEdit>> {
+        let uri = &params.text_document_position.text_document.uri;
+
+        // Get IDE client, workspace root, config and open documents, then immediately drop the lock
+        let (ide, workspace_root, config, open_documents) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.workspace_root.clone(),
+                state.config.clone(),
+                state.document_contents.clone(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(workspace_root), Some(config), Some(content)) =
+            (ide, workspace_root, config, open_documents.get(uri))
+        else {
+            return Ok(None);
+        };
+
+        rename::rename(
+            &ide,
+            &workspace_root,
+            &config,
+            content,
+            params.text_document_position.position,
+            &params.new_name,
+            &open_documents,
+        )
+        .await
+        .map_err(|e| tower_lsp::jsonrpc::Error::invalid_params(e.to_string()))
+    }
+
+    async fn completion(&self, params: CompletionParams) -> LspResult<Option<CompletionResponse>> {
+        let uri = &params.text_document_position.text_document.uri;
+
+        // Get IDE client and document content, then immediately drop the lock
+        let (ide, document_content) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.document_contents.get(uri).cloned(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(content)) = (ide, document_content) else {
+            return Ok(None);
+        };
+
+        match completion::complete(&ide, uri, &content, params.text_document_position.position)
+            .await
+        {
+            Ok(items) => Ok(Some(CompletionResponse::Array(items))),
+            Err(e) => {
+                self.log_ide_error("Completion failed", &e).await;
+                Ok(None)
+            }
+        }
+    }
+
+    async fn completion_resolve(&self, item: CompletionItem) -> LspResult<CompletionItem> {
+        let auto_import = item
+            .data
+            .clone()
+            .and_then(|data| serde_json::from_value::<completion::AutoImport>(data).ok());
+
+        let Some(auto_import) = auto_import else {
+            return Ok(item);
+        };
+
+        // Get IDE client and document content, then immediately drop the lock
+        let (ide, document_content) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.document_contents.get(&auto_import.uri).cloned(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(content)) = (ide, document_content) else {
+            return Ok(item);
+        };
+
+        match completion::resolve(&ide, &content, item.clone()).await {
+            Ok(item) => Ok(item),
+            Err(e) => {
+                self.log_ide_error("Failed to resolve auto-import", &e)
+                    .await;
+                Ok(item)
+            }
         }
+    }
+
+    async fn code_action(&self, params: CodeActionParams) -> LspResult<Option<CodeActionResponse>> {
+        let uri = &params.text_document.uri;
+
+        // Clone errors, IDE client and document content and immediately drop the lock to avoid deadlock
+        let (errors, ide, document_content, resolve_support) = {
+            let state = self.state.lock().await;
+            (
+                state.diagnostics.errors(uri),
+                state.ide_server.client.clone(),
+                state.document_contents.get(uri).cloned(),
+                state.code_action_resolve_support,
+            )
+        }; // Lock is dropped here
 
         // Generate code actions for errors that overlap with the requested range
         let mut code_actions = code_actions::generate_code_actions(&params, &errors);
@@ -561,6 +1171,43 @@ impl LanguageServer for Backend {
             }
         }
 
+        // Import fixes and refactorings that need the IDE server
+        if let (Some(ide), Some(content)) = (ide, document_content) {
+            match code_actions::missing_import_actions(&ide, &params, &content, &errors).await {
+                Ok(actions) => code_actions.extend(actions),
+                Err(e) => {
+                    self.log_ide_error("Failed to find imports for unknown names", &e)
+                        .await;
+                }
+            }
+
+            match code_actions::fill_hole_actions(&ide, &params, &content, &errors).await {
+                Ok(actions) => code_actions.extend(actions),
+                Err(e) => {
+                    self.log_ide_error("Failed to find values for typed holes", &e)
+                        .await;
+                }
+            }
+
+            code_actions.extend(code_actions::case_split_action(
+                uri,
+                &content,
+                &params.range,
+            ));
+            code_actions.extend(code_actions::add_clause_action(
+                uri,
+                &content,
+                &params.range,
+            ));
+
+            // Clients that cannot resolve code actions need the edits up front
+            if !resolve_support {
+                code_actions = self
+                    .resolve_code_actions_eagerly(&ide, &content, code_actions)
+                    .await;
+            }
+        }
+
         Ok(Some(
             code_actions
                 .into_iter()
@@ -569,6 +1216,37 @@ impl LanguageServer for Backend {
         ))
     }
 
+    async fn code_action_resolve(&self, mut action: CodeAction) -> LspResult<CodeAction> {
+        let Some(data) = action
+            .data
+            .clone()
+            .and_then(|data| serde_json::from_value::<code_actions::ResolveData>(data).ok())
+        else {
+            return Ok(action);
+        };
+
+        // Get IDE client and document content, then immediately drop the lock
+        let (ide, document_content) = {
+            let state = self.state.lock().await;
+            (
+                state.ide_server.client.clone(),
+                state.document_contents.get(data.uri()).cloned(),
+            )
+        }; // Lock is dropped here
+
+        let (Some(ide), Some(content)) = (ide, document_content) else {
+            return Err(tower_lsp::jsonrpc::Error::invalid_params(
+                "IDE server not available",
+            ));
+        };
+
+        let edit = code_actions::resolve_code_action(&ide, &content, &data)
+            .await
+            .map_err(|e| tower_lsp::jsonrpc::Error::invalid_params(e.to_string()))?;
+        action.edit = Some(edit);
+        Ok(action)
+    }
+
     async fn execute_command(
         &self,
         params: ExecuteCommandParams,
@@ -585,6 +1263,33 @@ impl LanguageServer for Backend {
             return Ok(None);
         }
 
+        if params.command == "purescript.restartIdeServer" {
+            // An attached server is not ours to restart, so it reloads every module instead
+            let attached = {
+                let state = self.state.lock().await;
+                state
+                    .ide_server
+                    .client
+                    .clone()
+                    .filter(|_| state.ide_server.process.is_none())
+            };
+            if let Some(ide) = attached
+                && let Err(e) = ide_commands::reset(&ide).await
+            {
+                self.log_ide_error("Failed to reset IDE server", &e).await;
+            }
+
+            if let Err(e) = self.restart_server("Restart requested").await {
+                self.client
+                    .log_message(
+                        MessageType::ERROR,
+                        format!("Failed to restart IDE server: {}", e),
+                    )
+                    .await;
+            }
+            return Ok(None);
+        }
+
         let args = if params.arguments.is_empty() {
             None
         } else {
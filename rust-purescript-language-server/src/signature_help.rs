@@ -0,0 +1,136 @@
+use crate::document;
+use crate::ide_server::{CompletionInfo, IdeClient, commands as ide_commands};
+use anyhow::Result;
+use lsp_types::{
+    Documentation, MarkupContent, MarkupKind, ParameterInformation, ParameterLabel, Position,
+    SignatureHelp, SignatureInformation,
+};
+
+/// Get signature help for the function application at a position
+///
+/// The function's type comes from the IDE server; the argument being typed is
+/// highlighted among the argument types of its signature.
+pub async fn signature_help(
+    ide: &IdeClient,
+    content: &str,
+    position: Position,
+) -> Result<Option<SignatureHelp>> {
+    let Some(application) = document::application_at(content, position) else {
+        return Ok(None);
+    };
+
+    let results = ide_commands::type_search_in_module(
+        ide,
+        content,
+        application.function.qualifier.as_deref(),
+        &application.function.name,
+    )
+    .await?;
+
+    // Values that are not functions have no arguments to help with
+    let results: Vec<CompletionInfo> = results
+        .into_iter()
+        .filter(|info| document::split_function_type(&info.type_info).len() > 1)
+        .collect();
+
+    if results.is_empty() {
+        return Ok(None);
+    }
+
+    Ok(Some(SignatureHelp {
+        signatures: results.iter().map(signature_information).collect(),
+        active_signature: Some(0),
+        active_parameter: Some(application.argument),
+    }))
+}
+
+/// Build a signature whose parameters are the argument types of a declaration
+///
+/// Parameters are given as offsets into the label, counted in UTF-16 code units as
+/// LSP expects.
+fn signature_information(info: &CompletionInfo) -> SignatureInformation {
+    let prefix = format!("{} :: ", info.identifier);
+    let label = format!("{}{}", prefix, info.type_info);
+    let utf16_offset = |index: usize| label[..prefix.len() + index].encode_utf16().count() as u32;
+
+    let ranges = document::function_type_ranges(&info.type_info);
+    let parameters = ranges
+        .iter()
+        .take(ranges.len().saturating_sub(1))
+        .map(|range| ParameterInformation {
+            label: ParameterLabel::LabelOffsets([
+                utf16_offset(range.start),
+                utf16_offset(range.end),
+            ]),
+            documentation: None,
+        })
+        .collect();
+
+    SignatureInformation {
+        documentation: info
+            .documentation
+            .as_deref()
+            .map(str::trim)
+            .filter(|doc| !doc.is_empty())
+            .map(|doc| {
+                Documentation::MarkupContent(MarkupContent {
+                    kind: MarkupKind::Markdown,
+                    value: doc.to_string(),
+                })
+            }),
+        label,
+        parameters: Some(parameters),
+        active_parameter: None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn parameter_labels(identifier: &str, type_info: &str) -> Vec<[u32; 2]> {
+        let info: CompletionInfo = serde_json::from_value(json!({
+            "module": "Main",
+            "identifier": identifier,
+            "type": type_info,
+            "expandedType": null,
+            "definedAt": null,
+            "documentation": null,
+            "exportedFrom": [],
+            "declarationType": "value"
+        }))
+        .unwrap();
+
+        signature_information(&info)
+            .parameters
+            .unwrap()
+            .into_iter()
+            .map(|parameter| match parameter.label {
+                ParameterLabel::LabelOffsets(offsets) => offsets,
+                ParameterLabel::Simple(label) => panic!("Expected offsets, got {}", label),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_parameter_with_its_own_constraint() {
+        // "apply :: forall a. Show a => " is 29 code units long
+        assert_eq!(
+            parameter_labels(
+                "apply",
+                "forall a. Show a => (forall b. Eq b => b -> a) -> String"
+            ),
+            vec![[29, 55]]
+        );
+    }
+
+    #[test]
+    fn test_offsets_count_utf16_code_units() {
+        // The emoji is one char but two UTF-16 code units
+        assert_eq!(
+            parameter_labels("label", "Proxy \"🏷\" -> String -> String"),
+            vec![[9, 19], [23, 29]]
+        );
+    }
+}
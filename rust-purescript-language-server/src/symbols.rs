@@ -0,0 +1,413 @@
+use crate::definition;
+use crate::document;
+use crate::ide_server::{CompletionInfo, IdeClient, commands as ide_commands};
+use anyhow::Result;
+use lsp_types::{DocumentSymbol, Position, Range, SymbolInformation, SymbolKind};
+use regex::Regex;
+use std::sync::LazyLock;
+
+/// Maximum number of workspace symbols returned per query
+const MAX_WORKSPACE_SYMBOLS: usize = 100;
+
+/// An indented `name :: Type` line in a class declaration
+static CLASS_MEMBER_RE: LazyLock<Regex> =
+    LazyLock::new(|| Regex::new(r"^\s+([a-z_][A-Za-z0-9_']*)\s*::\s*(.*)$").unwrap());
+
+/// A top-level declaration: its first line and the last line belonging to it
+struct Block {
+    start: usize,
+    end: usize,
+}
+
+/// Build the outline of a module from its source text
+///
+/// This is a line-based parser rather than the compiler, so the outline stays
+/// available while the module does not compile. Top-level declarations start at
+/// column 0 and extend over the indented lines that follow them.
+pub fn document_symbols(text: &str) -> Vec<DocumentSymbol> {
+    let lines: Vec<&str> = text.lines().collect();
+    let mut symbols: Vec<DocumentSymbol> = Vec::new();
+
+    for block in top_level_blocks(&lines) {
+        let Some(symbol) = block_symbol(&lines, &block) else {
+            continue;
+        };
+
+        // Signatures and clauses of the same value form a single symbol
+        if let Some(previous) = symbols.last_mut()
+            && is_value_kind(previous.kind)
+            && is_value_kind(symbol.kind)
+            && previous.name == symbol.name
+        {
+            previous.range.end = symbol.range.end;
+            if previous.detail.is_none() {
+                previous.detail = symbol.detail;
+            }
+            continue;
+        }
+
+        symbols.push(symbol);
+    }
+
+    symbols
+}
+
+fn is_value_kind(kind: SymbolKind) -> bool {
+    kind == SymbolKind::FUNCTION || kind == SymbolKind::CONSTANT
+}
+
+/// Split the module into top-level declaration blocks, skipping comments
+fn top_level_blocks(lines: &[&str]) -> Vec<Block> {
+    let mut blocks: Vec<Block> = Vec::new();
+    let mut in_block_comment = false;
+
+    for (index, line) in lines.iter().enumerate() {
+        if in_block_comment {
+            in_block_comment = !line.contains("-}");
+            continue;
+        }
+        if line.starts_with("{-") {
+            in_block_comment = !line.contains("-}");
+            continue;
+        }
+
+        let trimmed = line.trim();
+        if trimmed.is_empty() || trimmed.starts_with("--") {
+            continue;
+        }
+
+        if line.starts_with(char::is_whitespace) {
+            // Continuation of the current declaration
+            if let Some(block) = blocks.last_mut() {
+                block.end = index;
+            }
+        } else {
+            blocks.push(Block {
+                start: index,
+                end: index,
+            });
+        }
+    }
+
+    blocks
+}
+
+/// Create the symbol for a declaration block, if it declares anything
+#[allow(deprecated)]
+fn block_symbol(lines: &[&str], block: &Block) -> Option<DocumentSymbol> {
+    let line = lines[block.start];
+    let declaration = join_block(lines, block);
+    let words: Vec<&str> = line.split_whitespace().collect();
+
+    let (kind, name, detail, children) = match words.as_slice() {
+        ["module", ..] | ["import", ..] => return None,
+        // Kind signatures such as `data Maybe :: Type -> Type` precede the declaration
+        [_, _, "::", ..] if matches!(words[0], "data" | "newtype" | "type" | "class") => {
+            return None;
+        }
+        ["foreign", "import", "data", name, ..] => {
+            (SymbolKind::STRUCT, name.to_string(), None, vec![])
+        }
+        ["foreign", "import", name, ..] => (
+            SymbolKind::FUNCTION,
+            name.to_string(),
+            signature_detail(&declaration),
+            vec![],
+        ),
+        ["data", name, ..] | ["newtype", name, ..] => (
+            SymbolKind::STRUCT,
+            name.to_string(),
+            None,
+            constructor_symbols(lines, block, &declaration),
+        ),
+        ["type", name, ..] => (
+            SymbolKind::TYPE_PARAMETER,
+            name.to_string(),
+            declaration
+                .split_once('=')
+                .map(|(_, synonym)| synonym.trim().to_string()),
+            vec![],
+        ),
+        ["class", ..] => (
+            SymbolKind::INTERFACE,
+            class_name(&declaration)?,
+            None,
+            class_member_symbols(lines, block),
+        ),
+        ["instance", ..] | ["derive", ..] | ["else", "instance", ..] => (
+            SymbolKind::OBJECT,
+            instance_name(&declaration)?,
+            None,
+            vec![],
+        ),
+        ["infix", ..] | ["infixl", ..] | ["infixr", ..] => {
+            let (_, operator) = declaration.rsplit_once(" as ")?;
+            (
+                SymbolKind::OPERATOR,
+                operator.trim().to_string(),
+                Some(declaration.clone()),
+                vec![],
+            )
+        }
+        [first, ..] if first.starts_with(|c: char| c.is_lowercase() || c == '_') => {
+            let name: String = first
+                .chars()
+                .take_while(|c| c.is_alphanumeric() || *c == '_' || *c == '\'')
+                .collect();
+            let detail = signature_detail(&declaration);
+            // Without a signature, a clause that binds no parameters is a constant
+            let kind = match &detail {
+                Some(signature) if !signature.contains("->") => SymbolKind::CONSTANT,
+                None if declaration[name.len()..]
+                    .trim_start()
+                    .starts_with(['=', '|']) =>
+                {
+                    SymbolKind::CONSTANT
+                }
+                _ => SymbolKind::FUNCTION,
+            };
+            (kind, name, detail, vec![])
+        }
+        _ => return None,
+    };
+
+    let selection_range = word_range(lines, block.start, &name)
+        .unwrap_or_else(|| line_range(block.start, lines[block.start]));
+
+    Some(DocumentSymbol {
+        name,
+        detail,
+        kind,
+        tags: None,
+        deprecated: None,
+        range: Range {
+            start: Position {
+                line: block.start as u32,
+                character: 0,
+            },
+            end: Position {
+                line: block.end as u32,
+                character: lines[block.end].chars().count() as u32,
+            },
+        },
+        selection_range,
+        children: if children.is_empty() {
+            None
+        } else {
+            Some(children)
+        },
+    })
+}
+
+/// Join the lines of a block into one line of code, without comments
+fn join_block(lines: &[&str], block: &Block) -> String {
+    lines[block.start..=block.end]
+        .iter()
+        .map(|line| line.split("--").next().unwrap_or(line).trim())
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join(" ")
+}
+
+/// Get the type of a signature (`name :: Type`), if the block is one
+fn signature_detail(declaration: &str) -> Option<String> {
+    let (lhs, signature) = declaration.split_once("::")?;
+    // `foo x = a :: Int` is a clause with an annotation, not a signature
+    if lhs.contains('=') {
+        return None;
+    }
+    Some(signature.trim().to_string())
+}
+
+/// Get the class name, skipping any superclass constraints
+fn class_name(declaration: &str) -> Option<String> {
+    let head = declaration
+        .strip_prefix("class")?
+        .split(" where")
+        .next()?
+        .split('|')
+        .next()?;
+    let head = head.rsplit_once("<=").map(|(_, head)| head).unwrap_or(head);
+    head.split_whitespace().next().map(str::to_string)
+}
+
+/// Get the instance head, e.g. `Show Foo` or `showFoo :: Show Foo`
+fn instance_name(declaration: &str) -> Option<String> {
+    let start = declaration.find("instance")? + "instance".len();
+    let head = declaration[start..].split(" where").next()?.trim();
+    (!head.is_empty()).then(|| head.to_string())
+}
+
+/// Get the constructors of a `data` or `newtype` declaration
+#[allow(deprecated)]
+fn constructor_symbols(lines: &[&str], block: &Block, declaration: &str) -> Vec<DocumentSymbol> {
+    let Some((_, constructors)) = declaration.split_once('=') else {
+        return vec![];
+    };
+
+    // Constructors come after the first `=`, which matters for `newtype Name = Name String`
+    let Some((equals_line, equals_column)) = (block.start..=block.end).find_map(|line| {
+        lines[line]
+            .find('=')
+            .map(|index| (line, lines[line][..index].chars().count() as u32))
+    }) else {
+        return vec![];
+    };
+
+    split_constructors(constructors)
+        .into_iter()
+        .filter_map(|constructor| {
+            let name = constructor.split_whitespace().next()?.to_string();
+            let range = (equals_line..=block.end).find_map(|line| {
+                let (start, end) = document::find_word(lines[line], &name)
+                    .into_iter()
+                    .find(|(start, _)| line > equals_line || *start > equals_column)?;
+                Some(Range {
+                    start: Position {
+                        line: line as u32,
+                        character: start,
+                    },
+                    end: Position {
+                        line: line as u32,
+                        character: end,
+                    },
+                })
+            })?;
+
+            Some(DocumentSymbol {
+                name,
+                detail: Some(constructor.trim().to_string()),
+                kind: SymbolKind::CONSTRUCTOR,
+                tags: None,
+                deprecated: None,
+                range,
+                selection_range: range,
+                children: None,
+            })
+        })
+        .collect()
+}
+
+/// Split constructors on `|` outside of brackets
+fn split_constructors(constructors: &str) -> Vec<String> {
+    let mut parts = Vec::new();
+    let mut depth = 0i32;
+    let mut current = String::new();
+
+    for c in constructors.chars() {
+        match c {
+            '(' | '[' | '{' => depth += 1,
+            ')' | ']' | '}' => depth -= 1,
+            '|' if depth == 0 => {
+                parts.push(std::mem::take(&mut current));
+                continue;
+            }
+            _ => {}
+        }
+        current.push(c);
+    }
+    parts.push(current);
+
+    parts
+        .into_iter()
+        .filter(|part| !part.trim().is_empty())
+        .collect()
+}
+
+/// Get the members of a class declaration (indented `name :: Type` lines)
+#[allow(deprecated)]
+fn class_member_symbols(lines: &[&str], block: &Block) -> Vec<DocumentSymbol> {
+    (block.start + 1..=block.end)
+        .filter_map(|line| {
+            let captures = CLASS_MEMBER_RE.captures(lines[line])?;
+            let name = captures[1].to_string();
+            let range = word_range(lines, line, &name)?;
+
+            Some(DocumentSymbol {
+                name,
+                detail: Some(captures[2].trim().to_string()),
+                kind: SymbolKind::METHOD,
+                tags: None,
+                deprecated: None,
+                range: line_range(line, lines[line]),
+                selection_range: range,
+                children: None,
+            })
+        })
+        .collect()
+}
+
+/// Range of the first whole-word occurrence of `word` on a line
+fn word_range(lines: &[&str], line: usize, word: &str) -> Option<Range> {
+    let (start, end) = document::find_word(lines[line], word).into_iter().next()?;
+    Some(Range {
+        start: Position {
+            line: line as u32,
+            character: start,
+        },
+        end: Position {
+            line: line as u32,
+            character: end,
+        },
+    })
+}
+
+fn line_range(line: usize, text: &str) -> Range {
+    Range {
+        start: Position {
+            line: line as u32,
+            character: 0,
+        },
+        end: Position {
+            line: line as u32,
+            character: text.chars().count() as u32,
+        },
+    }
+}
+
+/// Search declarations across the project and its dependencies by fuzzy name
+///
+/// Backed by the IDE server, so only modules it has loaded are searched. The
+/// defining module is reported as the symbol's container.
+#[allow(deprecated)]
+pub async fn workspace_symbols(
+    ide: &IdeClient,
+    workspace_root: &str,
+    query: &str,
+) -> Result<Vec<SymbolInformation>> {
+    if query.is_empty() {
+        return Ok(vec![]);
+    }
+
+    let results = ide_commands::complete_fuzzy(ide, query, MAX_WORKSPACE_SYMBOLS).await?;
+
+    Ok(results
+        .iter()
+        .filter_map(|info| {
+            let location = definition::span_to_location(info.defined_at.as_ref()?, workspace_root)?;
+            Some(SymbolInformation {
+                name: info.identifier.clone(),
+                kind: symbol_kind(info),
+                tags: None,
+                deprecated: None,
+                location,
+                container_name: Some(info.module.clone()),
+            })
+        })
+        .collect())
+}
+
+/// Map the IDE server's declaration type to a symbol kind, matching the outline
+fn symbol_kind(info: &CompletionInfo) -> SymbolKind {
+    match info.declaration_type.as_deref() {
+        Some("value") if info.type_info.contains("->") => SymbolKind::FUNCTION,
+        Some("value") => SymbolKind::CONSTANT,
+        Some("type") => SymbolKind::STRUCT,
+        Some("synonym") => SymbolKind::TYPE_PARAMETER,
+        Some("dataconstructor") => SymbolKind::CONSTRUCTOR,
+        Some("typeclass") => SymbolKind::INTERFACE,
+        Some("valueoperator") | Some("typeoperator") => SymbolKind::OPERATOR,
+        Some("module") => SymbolKind::MODULE,
+        _ => SymbolKind::VARIABLE,
+    }
+}
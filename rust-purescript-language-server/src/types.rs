@@ -1,22 +1,35 @@
 use crate::config::Config;
-use crate::ide_server::RebuildError;
+use crate::diagnostic_store::DiagnosticStore;
+use crate::ide_server::IdeClient;
 use std::collections::HashMap;
-use tower_lsp::lsp_types::Url;
+use tower_lsp::lsp_types::{InlayHint, Url};
 
 /// IDE server state
 #[derive(Debug)]
 pub struct IdeServerState {
-    pub port: Option<u16>,
+    pub client: Option<IdeClient>,
     pub process: Option<std::process::Child>,
     pub working_dir: Option<String>,
+    /// Bumped whenever the process is replaced or stopped outside the supervisor,
+    /// so a supervisor watching an older process knows to stop
+    pub generation: u64,
+}
+
+impl IdeServerState {
+    /// Take the process to stop it, detaching its supervisor
+    pub fn take_process(&mut self) -> Option<std::process::Child> {
+        self.generation += 1;
+        self.process.take()
+    }
 }
 
 impl Default for IdeServerState {
     fn default() -> Self {
         Self {
-            port: None,
+            client: None,
             process: None,
             working_dir: None,
+            generation: 0,
         }
     }
 }
@@ -27,10 +40,14 @@ pub struct ServerState {
     pub config: Option<Config>,
     pub ide_server: IdeServerState,
     pub workspace_root: Option<String>,
-    pub document_errors: HashMap<Url, Vec<RebuildError>>,
-    pub last_build_errors: HashMap<Url, Vec<RebuildError>>,
+    pub diagnostics: DiagnosticStore,
     pub document_contents: HashMap<Url, String>,
     pub rebuild_counter: u64,
+    pub inlay_hint_refresh_support: bool,
+    /// Whether the client resolves code action edits lazily (`codeAction/resolve`)
+    pub code_action_resolve_support: bool,
+    /// Local binding hints computed for a document's content, reused until it changes
+    pub local_inlay_hints: HashMap<Url, (String, Vec<InlayHint>)>,
 }
 
 impl Default for ServerState {
@@ -39,10 +56,12 @@ impl Default for ServerState {
             config: None,
             ide_server: IdeServerState::default(),
             workspace_root: None,
-            document_errors: HashMap::new(),
-            last_build_errors: HashMap::new(),
+            diagnostics: DiagnosticStore::default(),
             document_contents: HashMap::new(),
             rebuild_counter: 0,
+            inlay_hint_refresh_support: false,
+            code_action_resolve_support: false,
+            local_inlay_hints: HashMap::new(),
         }
     }
 }
@@ -69,6 +88,19 @@ impl ServerState {
         self.config.as_ref().map(|c| c.formatter.clone())
     }
 
+    /// Get the enabled inlay hint categories (returns None if not initialized)
+    pub fn inlay_hints(&self) -> Option<crate::config::InlayHintsConfig> {
+        self.config.as_ref().map(|c| c.inlay_hints.clone())
+    }
+
+    /// Get the diagnostics filtering settings (nothing is filtered if not initialized)
+    pub fn diagnostics_config(&self) -> crate::config::DiagnosticsConfig {
+        self.config
+            .as_ref()
+            .map(|c| c.diagnostics.clone())
+            .unwrap_or_default()
+    }
+
     /// Check if the server is initialized with a valid config
     pub fn is_initialized(&self) -> bool {
         self.config.is_some()
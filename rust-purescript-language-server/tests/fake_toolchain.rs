@@ -0,0 +1,250 @@
+use anyhow::Result;
+use serde_json::{Value, json};
+use std::collections::HashMap;
+use std::ffi::OsString;
+use std::fs;
+use std::io::{BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use std::time::Duration;
+use tempfile::TempDir;
+
+/// Stand-ins for `purs` and `ragu`, so the server runs without the PureScript toolchain
+///
+/// `purs ide server` is a shell script that records the port it was given and
+/// sleeps; an `IdeStub` in the test process then listens on that port and answers
+/// the IDE commands. `ragu` prints fixed project paths and a scripted build output.
+pub struct FakeToolchain {
+    dir: TempDir,
+    pub ide: IdeStub,
+}
+
+impl FakeToolchain {
+    pub fn new() -> Result<Self> {
+        let dir = TempDir::new()?;
+        let port_file = dir.path().join("ide-port");
+        let build_output = dir.path().join("build-output.json");
+
+        write_script(
+            &dir.path().join("purs"),
+            &format!(
+                r#"#!/bin/sh
+case "$1" in
+  --version)
+    echo "0.15.15"
+    ;;
+  ide)
+    while [ $# -gt 0 ]; do
+      if [ "$1" = "-p" ]; then
+        echo "$2" > "{port_file}.tmp" && mv "{port_file}.tmp" "{port_file}"
+      fi
+      shift
+    done
+    exec sleep 3600
+    ;;
+  *)
+    exit 1
+    ;;
+esac
+"#,
+                port_file = port_file.display()
+            ),
+        )?;
+
+        write_script(
+            &dir.path().join("ragu"),
+            &format!(
+                r#"#!/bin/sh
+case "$1" in
+  output-dir)
+    echo "output"
+    ;;
+  sources)
+    echo "src/**/*.purs"
+    ;;
+  build)
+    if [ -f "{build_output}" ]; then
+      cat "{build_output}"
+    else
+      echo '{{"warnings":[],"errors":[]}}'
+    fi
+    ;;
+  *)
+    exit 1
+    ;;
+esac
+"#,
+                build_output = build_output.display()
+            ),
+        )?;
+
+        Ok(FakeToolchain {
+            ide: IdeStub::new(port_file),
+            dir,
+        })
+    }
+
+    /// `PATH` with the fake tools first
+    pub fn path_env(&self) -> OsString {
+        let mut paths = vec![self.dir.path().to_path_buf()];
+        if let Some(path) = std::env::var_os("PATH") {
+            paths.extend(std::env::split_paths(&path));
+        }
+        std::env::join_paths(paths).expect("PATH entries are valid")
+    }
+
+    /// Set the JSON `ragu build -- --json-errors` prints
+    pub fn set_build_output(&self, output: Value) -> Result<()> {
+        fs::write(
+            self.dir.path().join("build-output.json"),
+            output.to_string(),
+        )?;
+        Ok(())
+    }
+
+    /// Make the stub listen on a free port, as a `purs ide server` started outside
+    /// the language server would, and return the port
+    pub fn serve_external_ide(&self) -> Result<u16> {
+        let port = TcpListener::bind("127.0.0.1:0")?.local_addr()?.port();
+        fs::write(self.dir.path().join("ide-port"), port.to_string())?;
+
+        // Already running by the time the language server looks for it
+        let start = std::time::Instant::now();
+        while TcpStream::connect(("127.0.0.1", port)).is_err() {
+            anyhow::ensure!(
+                start.elapsed() < Duration::from_secs(5),
+                "IDE stub did not listen on port {}",
+                port
+            );
+            std::thread::sleep(Duration::from_millis(10));
+        }
+        Ok(port)
+    }
+}
+
+fn write_script(path: &Path, contents: &str) -> Result<()> {
+    fs::write(path, contents)?;
+
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        fs::set_permissions(path, fs::Permissions::from_mode(0o755))?;
+    }
+
+    Ok(())
+}
+
+/// In-process replacement for `purs ide server` that replays scripted responses
+///
+/// Commands without a scripted response succeed with an empty result. Every
+/// command received is recorded.
+pub struct IdeStub {
+    responses: Arc<Mutex<HashMap<String, Value>>>,
+    received: Arc<Mutex<Vec<Value>>>,
+    stopped: Arc<AtomicBool>,
+}
+
+impl IdeStub {
+    fn new(port_file: PathBuf) -> Self {
+        let stub = IdeStub {
+            responses: Arc::new(Mutex::new(HashMap::new())),
+            received: Arc::new(Mutex::new(Vec::new())),
+            stopped: Arc::new(AtomicBool::new(false)),
+        };
+
+        let responses = stub.responses.clone();
+        let received = stub.received.clone();
+        let stopped = stub.stopped.clone();
+        std::thread::spawn(move || serve(&port_file, &responses, &received, &stopped));
+
+        stub
+    }
+
+    /// Answer every `command` with a successful result
+    pub fn respond(&self, command: &str, result: Value) {
+        self.respond_with(
+            command,
+            json!({ "resultType": "success", "result": result }),
+        );
+    }
+
+    /// Answer every `command` with a full response, e.g. a failed rebuild
+    pub fn respond_with(&self, command: &str, response: Value) {
+        self.responses
+            .lock()
+            .unwrap()
+            .insert(command.to_string(), response);
+    }
+
+    /// The params of every `command` received so far
+    pub fn received(&self, command: &str) -> Vec<Value> {
+        self.received
+            .lock()
+            .unwrap()
+            .iter()
+            .filter(|request| request["command"] == command)
+            .map(|request| request["params"].clone())
+            .collect()
+    }
+}
+
+impl Drop for IdeStub {
+    fn drop(&mut self) {
+        self.stopped.store(true, Ordering::SeqCst);
+    }
+}
+
+/// Listen on the port the fake `purs` recorded last and answer one command per connection
+fn serve(
+    port_file: &Path,
+    responses: &Mutex<HashMap<String, Value>>,
+    received: &Mutex<Vec<Value>>,
+    stopped: &AtomicBool,
+) {
+    let mut listener: Option<(u16, TcpListener)> = None;
+
+    while !stopped.load(Ordering::SeqCst) {
+        // A restarted IDE server is given a new port
+        if let Some(port) = fs::read_to_string(port_file)
+            .ok()
+            .and_then(|port| port.trim().parse::<u16>().ok())
+            && listener
+                .as_ref()
+                .is_none_or(|(current, _)| *current != port)
+            && let Ok(new_listener) = TcpListener::bind(("127.0.0.1", port))
+        {
+            new_listener
+                .set_nonblocking(true)
+                .expect("listener can be made non-blocking");
+            listener = Some((port, new_listener));
+        }
+
+        let Some(Ok((stream, _))) = listener.as_ref().map(|(_, listener)| listener.accept()) else {
+            std::thread::sleep(Duration::from_millis(10));
+            continue;
+        };
+        let _ = stream.set_nonblocking(false);
+
+        let mut line = String::new();
+        // Connections without a command are liveness probes
+        if BufReader::new(&stream).read_line(&mut line).unwrap_or(0) == 0 {
+            continue;
+        }
+        let Ok(request) = serde_json::from_str::<Value>(&line) else {
+            continue;
+        };
+
+        let command = request["command"].as_str().unwrap_or_default().to_string();
+        received.lock().unwrap().push(request);
+        let response = responses
+            .lock()
+            .unwrap()
+            .get(&command)
+            .cloned()
+            .unwrap_or_else(|| json!({ "resultType": "success", "result": [] }));
+
+        let _ = writeln!(&stream, "{}", response);
+    }
+}
@@ -1,4 +1,5 @@
 use anyhow::Result;
+use fake_toolchain::FakeToolchain;
 use lsp_client::TestLspClient;
 use serde_json::json;
 use std::fs;
@@ -6,48 +7,40 @@ use std::path::Path;
 use std::time::Duration;
 use tempfile::TempDir;
 
+mod fake_toolchain;
 mod lsp_client;
 
+/// Copy the fixture workspace to a temporary directory the tests can modify
 fn setup_test_workspace() -> Result<TempDir> {
     let temp_dir = TempDir::new()?;
-    let workspace_path = temp_dir.path();
-
-    // Copy example project structure
-    let example_project = Path::new("../example-project");
-    println!("Example project path: {:?}", example_project);
-    println!("Example project exists: {}", example_project.exists());
-
-    // Create src directory
-    let src_dir = workspace_path.join("src");
-    fs::create_dir_all(&src_dir)?;
-
-    // Copy spago.yaml
-    fs::copy(
-        example_project.join("spago.yaml"),
-        workspace_path.join("spago.yaml"),
-    )?;
-
-    // Copy valid Main.purs
-    fs::copy(
-        example_project.join("src/Main.purs"),
-        src_dir.join("Main.purs"),
-    )?;
-
-    // Copy Test.purs
-    fs::copy(
-        example_project.join("src/Test.purs"),
-        src_dir.join("Test.purs"),
+    copy_dir(
+        &Path::new(env!("CARGO_MANIFEST_DIR")).join("tests/fixtures/workspace"),
+        temp_dir.path(),
     )?;
-
     Ok(temp_dir)
 }
 
+fn copy_dir(from: &Path, to: &Path) -> Result<()> {
+    fs::create_dir_all(to)?;
+    for entry in fs::read_dir(from)? {
+        let entry = entry?;
+        let target = to.join(entry.file_name());
+        if entry.file_type()?.is_dir() {
+            copy_dir(&entry.path(), &target)?;
+        } else {
+            fs::copy(entry.path(), target)?;
+        }
+    }
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_server_lifecycle() -> Result<()> {
     let temp_dir = setup_test_workspace()?;
     let workspace_path = temp_dir.path();
 
-    let mut client = TestLspClient::new(workspace_path)?;
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
 
     // Test initialize
     let result = client.initialize(workspace_path)?;
@@ -68,9 +61,9 @@ async fn test_diagnostics_with_broken_file() -> Result<()> {
     let temp_dir = setup_test_workspace()?;
     let workspace_path = temp_dir.path();
 
-    let mut client = TestLspClient::new(workspace_path)?;
-    client.initialize(workspace_path)?;
-    client.send_notification("initialized", json!(null))?;
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
 
     // Create a broken file
     let broken_content = r#"module Main where
@@ -113,9 +106,9 @@ async fn test_diagnostics_with_valid_file() -> Result<()> {
     let temp_dir = setup_test_workspace()?;
     let workspace_path = temp_dir.path();
 
-    let mut client = TestLspClient::new(workspace_path)?;
-    client.initialize(workspace_path)?;
-    client.send_notification("initialized", json!(null))?;
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
 
     // Ensure we have valid content
     let valid_content = r#"module Main where
@@ -161,9 +154,9 @@ async fn test_code_actions() -> Result<()> {
     let temp_dir = setup_test_workspace()?;
     let workspace_path = temp_dir.path();
 
-    let mut client = TestLspClient::new(workspace_path)?;
-    client.initialize(workspace_path)?;
-    client.send_notification("initialized", json!(null))?;
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
 
     // Create a file with a fixable error (missing import)
     let broken_content = r#"module Main where
@@ -220,14 +213,91 @@ main = do
     Ok(())
 }
 
+#[tokio::test]
+async fn test_code_action_resolve() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain
+        .ide
+        .respond("caseSplit", json!(["f Nothing = 0", "f (Just _) = 0"]));
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Data.Maybe (Maybe(..))\n\nf :: Maybe Int -> Int\nf m = 0\n"
+            }
+        }),
+    )?;
+
+    let code_actions = client.send_request(
+        "textDocument/codeAction",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "range": {
+                "start": { "line": 5, "character": 2 },
+                "end": { "line": 5, "character": 2 }
+            },
+            "context": {
+                "diagnostics": []
+            }
+        }),
+    )?;
+
+    // Listing the actions does not ask the IDE server for the split
+    let actions = code_actions
+        .as_array()
+        .expect("Expected code actions to be an array");
+    assert_eq!(actions.len(), 1);
+    assert_eq!(actions[0]["title"], "Case split 'm'");
+    assert!(actions[0].get("edit").is_none());
+    assert!(toolchain.ide.received("caseSplit").is_empty());
+
+    let resolved = client.send_request("codeAction/resolve", actions[0].clone())?;
+    assert_eq!(
+        resolved["edit"],
+        json!({
+            "changes": {
+                uri.clone(): [{
+                    "range": {
+                        "start": { "line": 5, "character": 0 },
+                        "end": { "line": 5, "character": 7 }
+                    },
+                    "newText": "f Nothing = 0\nf (Just _) = 0"
+                }]
+            }
+        })
+    );
+    let splits = toolchain.ide.received("caseSplit");
+    assert_eq!(splits.len(), 1);
+    assert_eq!(splits[0]["line"], "f m = 0");
+    assert_eq!(splits[0]["begin"], 2);
+    assert_eq!(splits[0]["end"], 3);
+    assert_eq!(splits[0]["type"], "Maybe Int");
+
+    client.shutdown()?;
+    Ok(())
+}
+
 #[tokio::test]
 async fn test_formatting() -> Result<()> {
     let temp_dir = setup_test_workspace()?;
     let workspace_path = temp_dir.path();
 
-    let mut client = TestLspClient::new(workspace_path)?;
-    client.initialize(workspace_path)?;
-    client.send_notification("initialized", json!(null))?;
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
 
     // Create a file with poor formatting
     let poorly_formatted_content = r#"module Main where
@@ -276,9 +346,9 @@ async fn test_basic_lsp_features() -> Result<()> {
     let temp_dir = setup_test_workspace()?;
     let workspace_path = temp_dir.path();
 
-    let mut client = TestLspClient::new(workspace_path)?;
-    client.initialize(workspace_path)?;
-    client.send_notification("initialized", json!(null))?;
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
 
     // Test that we can send a didOpen notification
     let uri = format!("file://{}/src/Main.purs", workspace_path.display());
@@ -322,3 +392,1375 @@ async fn test_basic_lsp_features() -> Result<()> {
     client.shutdown()?;
     Ok(())
 }
+
+#[tokio::test]
+async fn test_hover() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "type",
+        json!([{
+            "module": "Effect.Console",
+            "identifier": "log",
+            "type": "Logger",
+            "expandedType": "String -> Effect Unit",
+            "definedAt": null,
+            "documentation": "Write a message to the console.\n",
+            "exportedFrom": ["Effect.Console"],
+            "declarationType": "value"
+        }]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console as Console\n\nmain :: Effect Unit\nmain = Console.log \"test\"\n"
+            }
+        }),
+    )?;
+
+    // Hover over `log` in `Console.log`
+    let hover = client.send_request(
+        "textDocument/hover",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "position": { "line": 8, "character": 16 }
+        }),
+    )?;
+
+    assert_eq!(
+        hover["contents"]["value"],
+        "```purescript\nlog :: Logger\n```\n\nExpands to `String -> Effect Unit`\n\n*Effect.Console*\n\nWrite a message to the console."
+    );
+    // The range covers the name, not the qualifier
+    assert_eq!(
+        hover["range"],
+        json!({
+            "start": { "line": 8, "character": 15 },
+            "end": { "line": 8, "character": 18 }
+        })
+    );
+
+    // The qualifier is resolved to the module imported under it
+    let searches = toolchain.ide.received("type");
+    assert_eq!(searches.len(), 1);
+    assert_eq!(searches[0]["search"], "log");
+    assert_eq!(
+        searches[0]["filters"][1],
+        json!({ "filter": "modules", "params": { "modules": ["Effect.Console"] } })
+    );
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_completion() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "complete",
+        json!([{
+            "module": "Effect.Console",
+            "identifier": "log",
+            "type": "String -> Effect Unit",
+            "expandedType": "String -> Effect Unit",
+            "definedAt": null,
+            "documentation": null,
+            "exportedFrom": ["Effect.Console"],
+            "declarationType": "value"
+        }]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console as Console\n\nmain :: Effect Unit\nmain = Console.lo\n"
+            }
+        }),
+    )?;
+
+    // Complete `Console.lo`
+    let completions = client.send_request(
+        "textDocument/completion",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "position": { "line": 8, "character": 17 }
+        }),
+    )?;
+
+    let items = completions.as_array().expect("Expected a completion list");
+    assert_eq!(items.len(), 1, "{:?}", items);
+    assert_eq!(items[0]["label"], "log");
+    assert_eq!(items[0]["kind"], 3);
+    assert_eq!(items[0]["detail"], "String -> Effect Unit");
+    // Only the typed prefix is replaced, the qualifier stays
+    assert_eq!(
+        items[0]["textEdit"],
+        json!({
+            "range": {
+                "start": { "line": 8, "character": 15 },
+                "end": { "line": 8, "character": 17 }
+            },
+            "newText": "log"
+        })
+    );
+
+    // Searched among the modules imported as `Console`
+    let queries = toolchain.ide.received("complete");
+    assert_eq!(queries.len(), 1);
+    assert_eq!(
+        queries[0]["filters"][0],
+        json!({ "filter": "prefix", "params": { "search": "lo" } })
+    );
+    assert_eq!(queries[0]["filters"][1]["filter"], "dependencies");
+    assert_eq!(queries[0]["filters"][1]["params"]["qualifier"], "Console");
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_goto_definition() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let console = json!({
+        "module": "Effect.Console",
+        "identifier": "log",
+        "type": "String -> Effect Unit",
+        "expandedType": "String -> Effect Unit",
+        "definedAt": {
+            "name": ".spago/p/console-6.1.0/src/Effect/Console.purs",
+            "start": [14, 1],
+            "end": [14, 40]
+        },
+        "documentation": null,
+        "exportedFrom": ["Effect.Console"],
+        "declarationType": "value"
+    });
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond("type", json!([console]));
+    toolchain.ide.respond("complete", json!([console]));
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console (log)\n\nmain :: Effect Unit\nmain = log \"test\"\n"
+            }
+        }),
+    )?;
+
+    // Jump from the `Effect.Console` import to the module
+    let definition = client.send_request(
+        "textDocument/definition",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "position": { "line": 5, "character": 10 }
+        }),
+    )?;
+
+    let console_uri = format!(
+        "file://{}/.spago/p/console-6.1.0/src/Effect/Console.purs",
+        workspace_path.display()
+    );
+    assert_eq!(
+        definition,
+        json!({
+            "uri": console_uri,
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 0, "character": 0 }
+            }
+        })
+    );
+    let modules = toolchain.ide.received("complete");
+    assert_eq!(
+        modules[0]["filters"],
+        json!([{ "filter": "modules", "params": { "modules": ["Effect.Console"] } }])
+    );
+
+    // Jump from `log` to its declaration
+    let definition = client.send_request(
+        "textDocument/definition",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "position": { "line": 8, "character": 8 }
+        }),
+    )?;
+
+    assert_eq!(
+        definition,
+        json!({
+            "uri": console_uri,
+            "range": {
+                "start": { "line": 13, "character": 0 },
+                "end": { "line": 13, "character": 39 }
+            }
+        })
+    );
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_goto_definition_of_type_and_constructor_sharing_a_name() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let declaration = |declaration_type: &str, start: u32| {
+        json!({
+            "module": "Main",
+            "identifier": "Name",
+            "type": "Type",
+            "expandedType": "Type",
+            "definedAt": { "name": "src/Main.purs", "start": [3, start], "end": [3, 27] },
+            "documentation": null,
+            "exportedFrom": ["Main"],
+            "declarationType": declaration_type
+        })
+    };
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "type",
+        json!([declaration("type", 9), declaration("dataconstructor", 16)]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nnewtype Name = Name String\n\ngreet :: Name -> Name\ngreet _ = Name \"World\"\n"
+            }
+        }),
+    )?;
+
+    let mut definition_at = |line: u32, character: u32| {
+        client.send_request(
+            "textDocument/definition",
+            json!({
+                "textDocument": { "uri": uri },
+                "position": { "line": line, "character": character }
+            }),
+        )
+    };
+    let location = |start: u32| {
+        json!({
+            "uri": uri,
+            "range": {
+                "start": { "line": 2, "character": start },
+                "end": { "line": 2, "character": 26 }
+            }
+        })
+    };
+
+    // `Name` in the signature is the type, in the expression the constructor
+    assert_eq!(definition_at(4, 10)?, location(8));
+    assert_eq!(definition_at(5, 11)?, location(15));
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_references() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    // The type and its constructor share a name; the IDE server lists the type first
+    let declaration = |declaration_type: &str, start: u32| {
+        json!({
+            "module": "Main",
+            "identifier": "Name",
+            "type": "Type",
+            "expandedType": "Type",
+            "definedAt": { "name": "src/Main.purs", "start": [8, start], "end": [8, 27] },
+            "documentation": null,
+            "exportedFrom": ["Main"],
+            "declarationType": declaration_type
+        })
+    };
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "type",
+        json!([declaration("type", 1), declaration("dataconstructor", 16)]),
+    );
+    toolchain.ide.respond(
+        "usages",
+        json!([
+            { "name": "src/Main.purs", "start": [8, 16], "end": [8, 20] },
+            { "name": "src/Main.purs", "start": [11, 8], "end": [11, 12] },
+            { "name": "src/Main.purs", "start": [14, 20], "end": [14, 24] }
+        ]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console (log)\n\nnewtype Name = Name String\n\ngreet :: Name -> String\ngreet (Name n) = n\n\nmain :: Effect Unit\nmain = log (greet (Name \"test\"))\n"
+            }
+        }),
+    )?;
+
+    // Find references to the `Name` constructor
+    let mut references = |include_declaration: bool| {
+        client.send_request(
+            "textDocument/references",
+            json!({
+                "textDocument": {
+                    "uri": uri
+                },
+                "position": { "line": 13, "character": 20 },
+                "context": { "includeDeclaration": include_declaration }
+            }),
+        )
+    };
+
+    let location = |line: u32, start: u32, end: u32| {
+        json!({
+            "uri": uri,
+            "range": {
+                "start": { "line": line, "character": start },
+                "end": { "line": line, "character": end }
+            }
+        })
+    };
+    assert_eq!(
+        references(true)?,
+        json!([
+            location(7, 15, 26),
+            location(10, 7, 11),
+            location(13, 19, 23)
+        ])
+    );
+
+    // The declaration site reported among the usages is left out as well
+    assert_eq!(
+        references(false)?,
+        json!([location(10, 7, 11), location(13, 19, 23)])
+    );
+
+    // Usages are looked up for the constructor, not the type
+    let usages = toolchain.ide.received("usages");
+    assert_eq!(usages.len(), 2);
+    assert_eq!(
+        usages[0],
+        json!({ "module": "Main", "namespace": "value", "identifier": "Name" })
+    );
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rename() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "type",
+        json!([{
+            "module": "Main",
+            "identifier": "greeting",
+            "type": "String",
+            "expandedType": "String",
+            "definedAt": { "name": "src/Main.purs", "start": [12, 1], "end": [12, 18] },
+            "documentation": null,
+            "exportedFrom": ["Main"],
+            "declarationType": "value"
+        }]),
+    );
+    toolchain.ide.respond(
+        "usages",
+        json!([{ "name": "src/Main.purs", "start": [9, 12], "end": [9, 20] }]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console (log)\n\nmain :: Effect Unit\nmain = log greeting\n\ngreeting :: String\ngreeting = \"test\"\n"
+            }
+        }),
+    )?;
+
+    // Rename the local `greeting` declaration
+    let edit = client.send_request(
+        "textDocument/rename",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "position": { "line": 8, "character": 13 },
+            "newName": "message"
+        }),
+    )?;
+
+    // The usage, the declaration and its type signature are renamed
+    let rename = |line: u32, start: u32, end: u32| {
+        json!({
+            "range": {
+                "start": { "line": line, "character": start },
+                "end": { "line": line, "character": end }
+            },
+            "newText": "message"
+        })
+    };
+    assert_eq!(
+        edit,
+        json!({
+            "changes": {
+                uri.clone(): [rename(8, 11, 19), rename(11, 0, 8), rename(10, 0, 8)]
+            }
+        })
+    );
+
+    let usages = toolchain.ide.received("usages");
+    assert_eq!(
+        usages,
+        vec![json!({ "module": "Main", "namespace": "value", "identifier": "greeting" })]
+    );
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_rename_type_and_constructor_sharing_a_name() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+    fs::write(
+        workspace_path.join("src/Types.purs"),
+        "module Types (Name(Name)) where\n\nnewtype Name = Name String\n",
+    )?;
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "type",
+        json!([
+            {
+                "module": "Types",
+                "identifier": "Name",
+                "type": "Type",
+                "expandedType": null,
+                "definedAt": { "name": "src/Types.purs", "start": [3, 9], "end": [3, 27] },
+                "documentation": null,
+                "exportedFrom": ["Types"],
+                "declarationType": "type"
+            },
+            {
+                "module": "Types",
+                "identifier": "Name",
+                "type": "String -> Name",
+                "expandedType": null,
+                "definedAt": { "name": "src/Types.purs", "start": [3, 16], "end": [3, 27] },
+                "documentation": null,
+                "exportedFrom": ["Types"],
+                "declarationType": "dataconstructor"
+            }
+        ]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let main_uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    let types_uri = format!("file://{}/src/Types.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": main_uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console (log)\nimport Types (Name(Name))\n\nmain :: Effect Unit\nmain = log (greet (Name \"World\"))\n\ngreet :: Name -> String\ngreet (Name name) = \"Hello \" <> name\n"
+            }
+        }),
+    )?;
+
+    let rename = |new_name: &str, line: u32, start: u32, end: u32| {
+        json!({
+            "range": {
+                "start": { "line": line, "character": start },
+                "end": { "line": line, "character": end }
+            },
+            "newText": new_name
+        })
+    };
+
+    // Renaming the type leaves the constructor in `Name(Name)` alone
+    toolchain.ide.respond(
+        "usages",
+        json!([{ "name": "src/Main.purs", "start": [12, 10], "end": [12, 14] }]),
+    );
+    let edit = client.send_request(
+        "textDocument/rename",
+        json!({
+            "textDocument": { "uri": main_uri },
+            "position": { "line": 11, "character": 10 },
+            "newName": "Person"
+        }),
+    )?;
+    assert_eq!(
+        edit,
+        json!({
+            "changes": {
+                main_uri.clone(): [rename("Person", 11, 9, 13), rename("Person", 6, 14, 18)],
+                types_uri.clone(): [rename("Person", 2, 8, 12), rename("Person", 0, 14, 18)]
+            }
+        })
+    );
+
+    // Renaming the constructor leaves the type alone
+    toolchain.ide.respond(
+        "usages",
+        json!([
+            { "name": "src/Main.purs", "start": [10, 20], "end": [10, 24] },
+            { "name": "src/Main.purs", "start": [13, 8], "end": [13, 12] }
+        ]),
+    );
+    let edit = client.send_request(
+        "textDocument/rename",
+        json!({
+            "textDocument": { "uri": main_uri },
+            "position": { "line": 9, "character": 20 },
+            "newName": "MkName"
+        }),
+    )?;
+    assert_eq!(
+        edit,
+        json!({
+            "changes": {
+                main_uri.clone(): [
+                    rename("MkName", 9, 19, 23),
+                    rename("MkName", 12, 7, 11),
+                    rename("MkName", 6, 19, 23)
+                ],
+                types_uri.clone(): [rename("MkName", 2, 15, 19), rename("MkName", 0, 19, 23)]
+            }
+        })
+    );
+
+    assert_eq!(
+        toolchain.ide.received("usages"),
+        vec![
+            json!({ "module": "Types", "namespace": "type", "identifier": "Name" }),
+            json!({ "module": "Types", "namespace": "value", "identifier": "Name" })
+        ]
+    );
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_document_symbols() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    // The outline must not depend on the module compiling
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\ndata Shape = Circle Number | Square Number\n\nclass Area a where\n  area :: a -> Number\n\nanswer = 42\n\ndouble x = x * 2\n\nmain :: Effect Unit\nmain = do\n  lg \"broken\"\n"
+            }
+        }),
+    )?;
+
+    let symbols = client.send_request(
+        "textDocument/documentSymbol",
+        json!({
+            "textDocument": {
+                "uri": uri
+            }
+        }),
+    )?;
+
+    let names: Vec<&str> = symbols
+        .as_array()
+        .expect("Expected document symbols to be an array")
+        .iter()
+        .filter_map(|symbol| symbol.get("name").and_then(|name| name.as_str()))
+        .collect();
+    assert_eq!(names, vec!["Shape", "Area", "answer", "double", "main"]);
+
+    // Values without a signature are constants (14) unless they bind parameters (12)
+    assert_eq!(symbols[2]["kind"], 14);
+    assert_eq!(symbols[3]["kind"], 12);
+    assert_eq!(symbols[4]["kind"], 14);
+
+    let constructors: Vec<&str> = symbols[0]["children"]
+        .as_array()
+        .expect("Expected data type to have constructor children")
+        .iter()
+        .filter_map(|symbol| symbol.get("name").and_then(|name| name.as_str()))
+        .collect();
+    assert_eq!(constructors, vec!["Circle", "Square"]);
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_workspace_symbols() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let declaration = |module: &str, identifier: &str, type_info: &str, defined_at| {
+        json!({
+            "module": module,
+            "identifier": identifier,
+            "type": type_info,
+            "expandedType": type_info,
+            "definedAt": defined_at,
+            "documentation": null,
+            "exportedFrom": [module],
+            "declarationType": "value"
+        })
+    };
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "complete",
+        json!([
+            declaration(
+                "Main",
+                "main",
+                "Effect Unit",
+                json!({ "name": "src/Main.purs", "start": [9, 1], "end": [10, 18] })
+            ),
+            declaration(
+                "Data.Person",
+                "makePerson",
+                "String -> Person",
+                json!({ "name": ".spago/p/person/src/Data/Person.purs", "start": [3, 1], "end": [3, 30] })
+            ),
+            // Declarations without a location cannot be listed
+            declaration("Prim", "main'", "Effect Unit", json!(null))
+        ]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let symbols = client.send_request("workspace/symbol", json!({ "query": "main" }))?;
+
+    let location = |path: &str, start: [u32; 2], end: [u32; 2]| {
+        json!({
+            "uri": format!("file://{}/{}", workspace_path.display(), path),
+            "range": {
+                "start": { "line": start[0], "character": start[1] },
+                "end": { "line": end[0], "character": end[1] }
+            }
+        })
+    };
+    assert_eq!(
+        symbols,
+        json!([
+            {
+                "name": "main",
+                "kind": 14,
+                "location": location("src/Main.purs", [8, 0], [9, 17]),
+                "containerName": "Main"
+            },
+            {
+                "name": "makePerson",
+                "kind": 12,
+                "location": location(".spago/p/person/src/Data/Person.purs", [2, 0], [2, 29]),
+                "containerName": "Data.Person"
+            }
+        ])
+    );
+
+    let queries = toolchain.ide.received("complete");
+    assert_eq!(queries.len(), 1);
+    assert_eq!(queries[0]["filters"], json!([]));
+    assert_eq!(
+        queries[0]["matcher"],
+        json!({ "matcher": "flex", "params": { "search": "main" } })
+    );
+    assert_eq!(queries[0]["options"]["groupReexports"], true);
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_signature_help() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "type",
+        json!([{
+            "module": "Data.Foldable",
+            "identifier": "foldl",
+            "type": "forall a b f. Foldable f => (b -> a -> b) -> b -> f a -> b",
+            "expandedType": "forall a b f. Foldable f => (b -> a -> b) -> b -> f a -> b",
+            "definedAt": null,
+            "documentation": "Folds a structure from the left.\n",
+            "exportedFrom": ["Data.Foldable"],
+            "declarationType": "value"
+        }]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Data.Foldable (foldl)\n\ntotal :: Array Int -> Int\ntotal xs = foldl add 0 \n"
+            }
+        }),
+    )?;
+
+    // Signature help for the third argument of `foldl`
+    let help = client.send_request(
+        "textDocument/signatureHelp",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "position": { "line": 7, "character": 23 }
+        }),
+    )?;
+
+    assert_eq!(
+        help,
+        json!({
+            "signatures": [{
+                "label": "foldl :: forall a b f. Foldable f => (b -> a -> b) -> b -> f a -> b",
+                "documentation": { "kind": "markdown", "value": "Folds a structure from the left." },
+                "parameters": [
+                    { "label": [37, 50] },
+                    { "label": [54, 55] },
+                    { "label": [59, 62] }
+                ]
+            }],
+            "activeSignature": 0,
+            "activeParameter": 2
+        })
+    );
+
+    let queries = toolchain.ide.received("type");
+    assert_eq!(queries.len(), 1);
+    assert_eq!(queries[0]["search"], "foldl");
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_inlay_hints() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "rebuild",
+        json!([{
+            "allSpans": [],
+            "errorCode": "MissingTypeDeclaration",
+            "errorLink": "",
+            "filename": "src/Main.purs",
+            "message": "  No type declaration was provided for the top-level declaration of main.\n",
+            "moduleName": "Main",
+            "position": { "startLine": 8, "startColumn": 1, "endLine": 8, "endColumn": 18 },
+            "suggestion": { "replacement": "main :: Effect Unit\n", "replace_range": null }
+        }]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console (log)\n\nmain = log \"test\"\n"
+            }
+        }),
+    )?;
+    client.wait_for_notification("textDocument/publishDiagnostics", |params| {
+        params["diagnostics"][0]["code"] == "MissingTypeDeclaration"
+    })?;
+
+    let hints = client.send_request(
+        "textDocument/inlayHint",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 8, "character": 0 }
+            }
+        }),
+    )?;
+
+    // The missing signature is shown after the name and can be inserted above it
+    assert_eq!(
+        hints,
+        json!([{
+            "position": { "line": 7, "character": 4 },
+            "label": ":: Effect Unit",
+            "kind": 1,
+            "textEdits": [{
+                "range": {
+                    "start": { "line": 7, "character": 0 },
+                    "end": { "line": 7, "character": 0 }
+                },
+                "newText": "main :: Effect Unit\n"
+            }],
+            "paddingLeft": true
+        }])
+    );
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_binding_inlay_hints() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    // The wildcard added for `n` is on line 10, column 12 of the annotated module
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "rebuild",
+        json!([{
+            "allSpans": [],
+            "errorCode": "WildcardInferredType",
+            "errorLink": "",
+            "filename": "src/Main.purs",
+            "message": "  Wildcard type definition has the inferred type\n\n    Int\n\n  in value declaration main\n",
+            "moduleName": "Main",
+            "position": { "startLine": 10, "startColumn": 12, "endLine": 10, "endColumn": 13 },
+            "suggestion": null
+        }]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.set_settings(json!({ "inlayHints": { "letBindings": true } }));
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    let content = "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console (log)\n\nmain :: Effect Unit\nmain = do\n  let n = 42\n  log (show n)\n";
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": content
+            }
+        }),
+    )?;
+
+    let hints = client.send_request(
+        "textDocument/inlayHint",
+        json!({
+            "textDocument": {
+                "uri": uri
+            },
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 11, "character": 0 }
+            }
+        }),
+    )?;
+
+    assert_eq!(
+        hints,
+        json!([{
+            "position": { "line": 9, "character": 7 },
+            "label": ":: Int",
+            "kind": 1,
+            "paddingLeft": true
+        }])
+    );
+
+    // The annotated module is only typechecked, then the original one is loaded again
+    let rebuilds = toolchain.ide.received("rebuild");
+    let [.., annotated, restored] = rebuilds.as_slice() else {
+        panic!("Expected two rebuilds, got: {:?}", rebuilds);
+    };
+    let file_path = format!("{}/src/Main.purs", workspace_path.display());
+    assert_eq!(
+        annotated,
+        &json!({
+            "file": format!("data:{}", content.trim_end().replace("let n = 42", "let n :: _\n      n = 42")),
+            "actualFile": file_path,
+            "codegen": []
+        })
+    );
+    assert_eq!(
+        restored,
+        &json!({ "file": format!("data:{}", content), "actualFile": file_path, "codegen": [] })
+    );
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_local_binding_inlay_hints_without_fast_rebuild() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.set_settings(json!({
+        "inlayHints": { "letBindings": true },
+        "fastRebuildOnChange": false
+    }));
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nmain = do\n  let n = 42\n  pure n\n"
+            }
+        }),
+    )?;
+    client.send_request(
+        "textDocument/inlayHint",
+        json!({
+            "textDocument": { "uri": uri },
+            "range": {
+                "start": { "line": 0, "character": 0 },
+                "end": { "line": 5, "character": 0 }
+            }
+        }),
+    )?;
+
+    // Without fast rebuilds the IDE server goes back to the file on disk
+    let rebuilds = toolchain.ide.received("rebuild");
+    let file_path = format!("{}/src/Main.purs", workspace_path.display());
+    assert_eq!(
+        rebuilds.last(),
+        Some(&json!({ "file": file_path, "codegen": [] }))
+    );
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_fast_rebuild_diagnostics() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+    let file_path = workspace_path.join("src/Main.purs");
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond_with(
+        "rebuild",
+        json!({
+            "resultType": "error",
+            "result": [{
+                "allSpans": [{
+                    "name": file_path.display().to_string(),
+                    "start": [10, 3],
+                    "end": [10, 5]
+                }],
+                "errorCode": "UnknownName",
+                "errorLink": "https://github.com/purescript/documentation/blob/master/errors/UnknownName.md",
+                "filename": file_path.display().to_string(),
+                "message": "  Unknown value lg\n",
+                "moduleName": "Main",
+                "position": { "startLine": 10, "startColumn": 3, "endLine": 10, "endColumn": 5 },
+                "suggestion": null
+            }]
+        }),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    let uri = format!("file://{}", file_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console (log)\n\nmain :: Effect Unit\nmain = do\n  lg \"test\"\n"
+            }
+        }),
+    )?;
+
+    let diagnostics =
+        client.wait_for_notification("textDocument/publishDiagnostics", |params| {
+            params["uri"] == uri.as_str()
+                && params["diagnostics"]
+                    .as_array()
+                    .is_some_and(|d| !d.is_empty())
+        })?;
+    let diagnostic = &diagnostics["diagnostics"][0];
+    assert_eq!(diagnostic["code"], "UnknownName");
+    assert_eq!(diagnostic["severity"], 1);
+    assert_eq!(
+        diagnostic["range"]["start"],
+        json!({ "line": 9, "character": 2 })
+    );
+
+    // Rebuilds on change send the open document's content
+    let rebuilds = toolchain.ide.received("rebuild");
+    assert!(rebuilds.iter().any(|params| {
+        params["file"]
+            .as_str()
+            .is_some_and(|file| file.starts_with("data:"))
+    }));
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_build_command_diagnostics() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.set_build_output(json!({
+        "warnings": [{
+            "allSpans": [{ "name": "src/Test.purs", "start": [5, 1], "end": [5, 6] }],
+            "errorCode": "UnusedName",
+            "errorLink": "https://github.com/purescript/documentation/blob/master/errors/UnusedName.md",
+            "filename": "src/Test.purs",
+            "message": "  Name name was introduced but not used.\n",
+            "moduleName": "Test",
+            "position": { "startLine": 6, "startColumn": 7, "endLine": 6, "endColumn": 11 },
+            "suggestion": null
+        }],
+        "errors": []
+    }))?;
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    client.send_request(
+        "workspace/executeCommand",
+        json!({ "command": "purescript.build", "arguments": [] }),
+    )?;
+
+    let uri = format!("file://{}/src/Test.purs", workspace_path.display());
+    let diagnostics = client
+        .wait_for_notification("textDocument/publishDiagnostics", |params| {
+            params["uri"] == uri.as_str()
+        })?;
+    let diagnostic = &diagnostics["diagnostics"][0];
+    assert_eq!(diagnostic["code"], "UnusedName");
+    assert_eq!(diagnostic["severity"], 2);
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_shutdown_quits_managed_ide_server() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    client.shutdown()?;
+    assert_eq!(toolchain.ide.received("quit").len(), 1);
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_restart_ide_server_command() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+
+    client.send_request(
+        "workspace/executeCommand",
+        json!({ "command": "purescript.restartIdeServer", "arguments": [] }),
+    )?;
+    client.wait_for_notification("purescript/ideServerStatus", |params| {
+        params["status"] == "running"
+    })?;
+
+    // A managed server is stopped and started again rather than reset
+    assert!(toolchain.ide.received("reset").is_empty());
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_restart_command_resets_external_ide_server() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    let port = toolchain.serve_external_ide()?;
+    fs::write(workspace_path.join(".psc-ide-port"), port.to_string())?;
+    toolchain
+        .ide
+        .respond("cwd", json!(workspace_path.display().to_string()));
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.set_settings(json!({ "externalIdeServer": { "portFile": ".psc-ide-port" } }));
+    client.initialize_workspace(workspace_path)?;
+    let loads = toolchain.ide.received("load").len();
+
+    client.send_request(
+        "workspace/executeCommand",
+        json!({ "command": "purescript.restartIdeServer", "arguments": [] }),
+    )?;
+    client.wait_for_notification("purescript/ideServerStatus", |params| {
+        params["status"] == "running"
+    })?;
+
+    assert_eq!(toolchain.ide.received("reset").len(), 1);
+    assert_eq!(toolchain.ide.received("load").len(), loads + 1);
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_attach_to_external_ide_server() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    let port = toolchain.serve_external_ide()?;
+    fs::write(workspace_path.join(".psc-ide-port"), port.to_string())?;
+    toolchain
+        .ide
+        .respond("cwd", json!(workspace_path.display().to_string()));
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.set_settings(json!({ "externalIdeServer": { "portFile": ".psc-ide-port" } }));
+    client.initialize_workspace(workspace_path)?;
+
+    let status = client.wait_for_notification("purescript/ideServerStatus", |params| {
+        params["status"] == "running"
+    })?;
+    assert_eq!(status["port"], port);
+
+    // Probed, and asked to load the project since it had no modules loaded
+    assert_eq!(toolchain.ide.received("cwd").len(), 1);
+    assert_eq!(
+        toolchain.ide.received("list"),
+        vec![
+            json!({ "type": "loadedModules" }),
+            json!({ "type": "availableModules" })
+        ]
+    );
+    assert!(!toolchain.ide.received("load").is_empty());
+
+    // The attached server belongs to whoever started it
+    client.shutdown()?;
+    assert!(toolchain.ide.received("quit").is_empty());
+
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_configuration_change_without_restart() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+    let loads = toolchain.ide.received("load").len();
+
+    // Inlay hint settings are read when hints are requested, so purs keeps running
+    client.set_settings(json!({ "inlayHints": { "lambdaParameters": true } }));
+    client.send_notification(
+        "workspace/didChangeConfiguration",
+        json!({ "settings": {} }),
+    )?;
+    client.wait_for_notification("window/logMessage", |params| {
+        params["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("without restarting the IDE server"))
+    })?;
+    assert_eq!(toolchain.ide.received("load").len(), loads);
+
+    // Changing what the IDE server watches restarts it
+    client.set_settings(json!({
+        "inlayHints": { "lambdaParameters": true },
+        "externalIdeServer": { "port": toolchain.serve_external_ide()? }
+    }));
+    client.send_notification(
+        "workspace/didChangeConfiguration",
+        json!({ "settings": {} }),
+    )?;
+    client.wait_for_notification("window/logMessage", |params| {
+        params["message"]
+            .as_str()
+            .is_some_and(|message| message.contains("restarting IDE server"))
+    })?;
+
+    client.shutdown()?;
+    Ok(())
+}
+
+#[tokio::test]
+async fn test_diagnostics_configuration_change() -> Result<()> {
+    let temp_dir = setup_test_workspace()?;
+    let workspace_path = temp_dir.path();
+
+    let toolchain = FakeToolchain::new()?;
+    toolchain.ide.respond(
+        "rebuild",
+        json!([{
+            "allSpans": [],
+            "errorCode": "MissingTypeDeclaration",
+            "errorLink": "",
+            "filename": "src/Main.purs",
+            "message": "  No type declaration was provided for the top-level declaration of main.\n",
+            "moduleName": "Main",
+            "position": { "startLine": 8, "startColumn": 1, "endLine": 8, "endColumn": 18 },
+            "suggestion": { "replacement": "main :: Effect Unit\n", "replace_range": null }
+        }]),
+    );
+
+    let mut client = TestLspClient::new(workspace_path, &toolchain.path_env())?;
+    client.initialize_workspace(workspace_path)?;
+    let loads = toolchain.ide.received("load").len();
+
+    let uri = format!("file://{}/src/Main.purs", workspace_path.display());
+    client.send_notification(
+        "textDocument/didOpen",
+        json!({
+            "textDocument": {
+                "uri": uri,
+                "languageId": "purescript",
+                "version": 1,
+                "text": "module Main where\n\nimport Prelude\n\nimport Effect (Effect)\nimport Effect.Console (log)\n\nmain = log \"test\"\n"
+            }
+        }),
+    )?;
+    client.wait_for_notification("textDocument/publishDiagnostics", |params| {
+        params["diagnostics"][0]["severity"] == 2
+    })?;
+
+    // The stored warning is published again as an error, without rebuilding
+    let rebuilds = toolchain.ide.received("rebuild").len();
+    client.set_settings(json!({ "diagnosticSeverity": { "MissingTypeDeclaration": "error" } }));
+    client.send_notification(
+        "workspace/didChangeConfiguration",
+        json!({ "settings": {} }),
+    )?;
+    let published = client.wait_for_notification("textDocument/publishDiagnostics", |params| {
+        params["diagnostics"][0]["severity"] == 1
+    })?;
+    assert_eq!(published["uri"], uri);
+    assert_eq!(published["diagnostics"].as_array().map(Vec::len), Some(1));
+    assert_eq!(toolchain.ide.received("rebuild").len(), rebuilds);
+    assert_eq!(toolchain.ide.received("load").len(), loads);
+
+    // Turning the code off clears it
+    client.set_settings(json!({ "diagnosticSeverity": { "MissingTypeDeclaration": "off" } }));
+    client.send_notification(
+        "workspace/didChangeConfiguration",
+        json!({ "settings": {} }),
+    )?;
+    client.wait_for_notification("textDocument/publishDiagnostics", |params| {
+        params["diagnostics"] == json!([])
+    })?;
+
+    client.shutdown()?;
+    Ok(())
+}
@@ -1,40 +1,30 @@
 use anyhow::{Context, Result};
 use serde_json::{Value, json};
+use std::ffi::OsStr;
 use std::io::{BufRead, BufReader, Read, Write};
 use std::path::Path;
 use std::process::{Child, ChildStdin, ChildStdout, Command, Stdio};
-use std::time::Duration;
+use std::sync::mpsc::{Receiver, RecvTimeoutError, channel};
+use std::time::{Duration, Instant};
+
+const RESPONSE_TIMEOUT: Duration = Duration::from_secs(10);
 
 pub struct TestLspClient {
     process: Child,
     stdin: ChildStdin,
-    stdout: BufReader<ChildStdout>,
+    messages: Receiver<Value>,
     next_id: i32,
+    /// Settings returned for the `purescriptRust` section of `workspace/configuration`
+    settings: Value,
+    /// Notifications received so far, in order
+    notifications: Vec<Value>,
 }
 
 impl TestLspClient {
-    pub fn new(workspace_path: &Path) -> Result<Self> {
-        // Try multiple possible binary paths
-        let possible_paths = [
-            "/Users/finnhodgkin/purescript-language-server/rust-purescript-language-server/target/debug/rust-purescript-language-server",
-            "./target/debug/rust-purescript-language-server",
-            "rust-purescript-language-server",
-        ];
-
-        let binary_path = possible_paths
-            .iter()
-            .find(|path| {
-                let exists = std::path::Path::new(path).exists();
-                println!("Checking path: {} -> exists: {}", path, exists);
-                exists || *path == &"rust-purescript-language-server"
-            })
-            .ok_or_else(|| {
-                anyhow::anyhow!("Could not find rust-purescript-language-server binary")
-            })?;
-
-        println!("Selected binary path: {}", binary_path);
-
-        let mut process = Command::new(binary_path)
+    /// Start the server in a workspace, finding its tools on `path`
+    pub fn new(workspace_path: &Path, path: &OsStr) -> Result<Self> {
+        let mut process = Command::new(env!("CARGO_BIN_EXE_rust-purescript-language-server"))
+            .env("PATH", path)
             .stdin(Stdio::piped())
             .stdout(Stdio::piped())
             .stderr(Stdio::piped())
@@ -45,14 +35,32 @@ impl TestLspClient {
         let stdin = process.stdin.take().unwrap();
         let stdout = BufReader::new(process.stdout.take().unwrap());
 
+        // Read on a separate thread so waits can time out
+        let (sender, messages) = channel();
+        std::thread::spawn(move || {
+            let mut stdout = stdout;
+            while let Ok(message) = read_message(&mut stdout) {
+                if sender.send(message).is_err() {
+                    break;
+                }
+            }
+        });
+
         Ok(TestLspClient {
             process,
             stdin,
-            stdout,
+            messages,
             next_id: 1,
+            settings: json!({}),
+            notifications: Vec::new(),
         })
     }
 
+    /// Set the `purescriptRust` settings returned to the server
+    pub fn set_settings(&mut self, settings: Value) {
+        self.settings = settings;
+    }
+
     pub fn initialize(&mut self, workspace_path: &Path) -> Result<Value> {
         let request = json!({
             "jsonrpc": "2.0",
@@ -74,8 +82,12 @@ impl TestLspClient {
                             "dynamicRegistration": false
                         },
                         "codeAction": {
-                            "dynamicRegistration": false
+                            "dynamicRegistration": false,
+                            "resolveSupport": { "properties": ["edit"] }
                         }
+                    },
+                    "workspace": {
+                        "configuration": true
                     }
                 },
                 "rootUri": format!("file://{}", workspace_path.display())
@@ -87,6 +99,21 @@ impl TestLspClient {
         self.wait_for_response(self.next_id - 1)
     }
 
+    /// Initialize, then send the configuration that starts the IDE server and wait
+    /// until it is running
+    pub fn initialize_workspace(&mut self, workspace_path: &Path) -> Result<Value> {
+        let result = self.initialize(workspace_path)?;
+        self.send_notification("initialized", json!(null))?;
+        self.send_notification(
+            "workspace/didChangeConfiguration",
+            json!({ "settings": {} }),
+        )?;
+        self.wait_for_notification("purescript/ideServerStatus", |params| {
+            params["status"] == "running"
+        })?;
+        Ok(result)
+    }
+
     pub fn send_notification(&mut self, method: &str, params: Value) -> Result<()> {
         let notification = if params.is_null() {
             json!({
@@ -117,6 +144,32 @@ impl TestLspClient {
         self.wait_for_response(self.next_id - 1)
     }
 
+    /// Wait for a notification matching `predicate`, including ones already received,
+    /// and return its params
+    pub fn wait_for_notification(
+        &mut self,
+        method: &str,
+        predicate: impl Fn(&Value) -> bool,
+    ) -> Result<Value> {
+        let matches = |notification: &Value| {
+            notification["method"] == method && predicate(&notification["params"])
+        };
+        if let Some(notification) = self.notifications.iter().find(|n| matches(n)) {
+            return Ok(notification["params"].clone());
+        }
+
+        let start = Instant::now();
+        loop {
+            let remaining = RESPONSE_TIMEOUT
+                .checked_sub(start.elapsed())
+                .with_context(|| format!("Timeout waiting for {} notification", method))?;
+            let message = self.next_message(remaining)?;
+            if message.get("id").is_none() && matches(&message) {
+                return Ok(message["params"].clone());
+            }
+        }
+    }
+
     pub fn shutdown(&mut self) -> Result<()> {
         // Send shutdown request
         let shutdown_request = json!({
@@ -169,60 +222,36 @@ impl TestLspClient {
         Ok(())
     }
 
-    fn read_message(&mut self) -> Result<Value> {
-        let mut content_length = None;
-        let mut line = String::new();
-
-        // Read headers with timeout
-        let start = std::time::Instant::now();
-        loop {
-            if start.elapsed() > Duration::from_secs(10) {
-                anyhow::bail!("Timeout reading message headers");
-            }
-
-            line.clear();
-            self.stdout.read_line(&mut line)?;
-            let line = line.trim();
-
-            if line.is_empty() {
-                break;
-            }
+    /// Receive the next message, answering requests from the server on the way
+    fn next_message(&mut self, timeout: Duration) -> Result<Value> {
+        let message = match self.messages.recv_timeout(timeout) {
+            Ok(message) => message,
+            Err(RecvTimeoutError::Timeout) => anyhow::bail!("Timeout reading message"),
+            Err(RecvTimeoutError::Disconnected) => anyhow::bail!("Server closed its output"),
+        };
 
-            if line.starts_with("Content-Length: ") {
-                content_length = Some(
-                    line.strip_prefix("Content-Length: ")
-                        .unwrap()
-                        .parse::<usize>()?,
-                );
-            }
+        if let (Some(id), Some(method)) = (message.get("id"), message.get("method")) {
+            let result = match method.as_str() {
+                Some("workspace/configuration") => {
+                    let items = message["params"]["items"].as_array().map_or(0, Vec::len);
+                    Value::Array(vec![self.settings.clone(); items])
+                }
+                _ => Value::Null,
+            };
+            self.send_message(&json!({
+                "jsonrpc": "2.0",
+                "id": id,
+                "result": result
+            }))?;
+        } else if message.get("id").is_none() {
+            self.notifications.push(message.clone());
         }
 
-        let content_length = content_length.context("Missing Content-Length header")?;
-
-        // Read message body
-        let mut buffer = vec![0; content_length];
-        self.stdout.read_exact(&mut buffer)?;
-
-        let message_str = String::from_utf8(buffer)?;
-        let message: Value = serde_json::from_str(&message_str)?;
-
         Ok(message)
     }
 
     fn wait_for_response(&mut self, expected_id: i32) -> Result<Value> {
-        loop {
-            let message = self.read_message()?;
-
-            if let Some(id) = message.get("id").and_then(|id| id.as_i64()) {
-                if id == expected_id as i64 {
-                    if let Some(result) = message.get("result") {
-                        return Ok(result.clone());
-                    } else if let Some(error) = message.get("error") {
-                        anyhow::bail!("LSP error: {}", error);
-                    }
-                }
-            }
-        }
+        self.wait_for_response_with_timeout(expected_id, RESPONSE_TIMEOUT)
     }
 
     fn wait_for_response_with_timeout(
@@ -230,28 +259,64 @@ impl TestLspClient {
         expected_id: i32,
         timeout: Duration,
     ) -> Result<Value> {
-        let start = std::time::Instant::now();
+        let start = Instant::now();
 
         loop {
-            if start.elapsed() > timeout {
+            let Some(remaining) = timeout.checked_sub(start.elapsed()) else {
                 anyhow::bail!("Timeout waiting for response with id {}", expected_id);
-            }
+            };
 
-            let message = self.read_message()?;
+            let message = self.next_message(remaining)?;
 
-            if let Some(id) = message.get("id").and_then(|id| id.as_i64()) {
-                if id == expected_id as i64 {
-                    if let Some(result) = message.get("result") {
-                        return Ok(result.clone());
-                    } else if let Some(error) = message.get("error") {
-                        anyhow::bail!("LSP error: {}", error);
-                    }
-                }
+            // Requests from the server share the id space, so skip them
+            if message.get("method").is_some()
+                || message.get("id").and_then(|id| id.as_i64()) != Some(expected_id as i64)
+            {
+                continue;
+            }
+
+            if let Some(result) = message.get("result") {
+                return Ok(result.clone());
+            } else if let Some(error) = message.get("error") {
+                anyhow::bail!("LSP error: {}", error);
             }
         }
     }
 }
 
+fn read_message(stdout: &mut BufReader<ChildStdout>) -> Result<Value> {
+    let mut content_length = None;
+    let mut line = String::new();
+
+    // Read headers
+    loop {
+        line.clear();
+        if stdout.read_line(&mut line)? == 0 {
+            anyhow::bail!("Server closed its output");
+        }
+        let line = line.trim();
+
+        if line.is_empty() {
+            break;
+        }
+
+        if let Some(length) = line.strip_prefix("Content-Length: ") {
+            content_length = Some(length.parse::<usize>()?);
+        }
+    }
+
+    let content_length = content_length.context("Missing Content-Length header")?;
+
+    // Read message body
+    let mut buffer = vec![0; content_length];
+    stdout.read_exact(&mut buffer)?;
+
+    let message_str = String::from_utf8(buffer)?;
+    let message: Value = serde_json::from_str(&message_str)?;
+
+    Ok(message)
+}
+
 impl Drop for TestLspClient {
     fn drop(&mut self) {
         let _ = self.process.kill();